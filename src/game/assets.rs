@@ -1,9 +1,15 @@
 use bevy::{
+    asset::LoadState,
     prelude::*,
     render::texture::{ImageLoaderSettings, ImageSampler},
     utils::HashMap,
 };
 
+use crate::screen::{
+    hex_map::cells::HexagonType,
+    voxel_world::registry::{BlockDefinitions, BlockDefinitionsLoader, BlockId, BlockSound},
+};
+
 pub(super) fn plugin(app: &mut App) {
     app.register_type::<HandleMap<ImageKey>>();
     app.init_resource::<HandleMap<ImageKey>>();
@@ -13,11 +19,23 @@ pub(super) fn plugin(app: &mut App) {
 
     app.register_type::<HandleMap<SoundtrackKey>>();
     app.init_resource::<HandleMap<SoundtrackKey>>();
+
+    // The loader has to exist before the data files start loading.
+    app.init_asset::<BlockDefinitions>();
+    app.init_asset_loader::<BlockDefinitionsLoader>();
+    app.register_type::<HandleMap<DataKey>>();
+    app.init_resource::<HandleMap<DataKey>>();
+
+    // These are filled from the block registry once it has loaded.
+    app.register_type::<HandleMap<BlockId>>();
+    app.register_type::<HandleMap<HexagonType>>();
+    app.register_type::<HandleMap<(BlockId, BlockSound)>>();
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Reflect)]
 pub enum ImageKey {
     Ducky,
+    HexOutline,
 }
 
 impl AssetKey for ImageKey {
@@ -27,15 +45,21 @@ impl AssetKey for ImageKey {
 impl FromWorld for HandleMap<ImageKey> {
     fn from_world(world: &mut World) -> Self {
         let asset_server = world.resource::<AssetServer>();
-        [(
-            ImageKey::Ducky,
-            asset_server.load_with_settings(
-                "images/ducky.png",
-                |settings: &mut ImageLoaderSettings| {
-                    settings.sampler = ImageSampler::nearest();
-                },
+        [
+            (
+                ImageKey::Ducky,
+                asset_server.load_with_settings(
+                    "images/ducky.png",
+                    |settings: &mut ImageLoaderSettings| {
+                        settings.sampler = ImageSampler::nearest();
+                    },
+                ),
+            ),
+            (
+                ImageKey::HexOutline,
+                asset_server.load("images/hexes/outline.png"),
             ),
-        )]
+        ]
         .into()
     }
 }
@@ -102,6 +126,39 @@ impl FromWorld for HandleMap<SoundtrackKey> {
     }
 }
 
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Reflect)]
+pub enum DataKey {
+    Blocks,
+}
+
+impl AssetKey for DataKey {
+    type Asset = BlockDefinitions;
+}
+
+impl FromWorld for HandleMap<DataKey> {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        [(DataKey::Blocks, asset_server.load("base.blocks.ron"))].into()
+    }
+}
+
+/// Block textures, keyed by the block they belong to.
+/// Only blocks with a texture file are in here, the others get a generated one.
+impl AssetKey for BlockId {
+    type Asset = Image;
+}
+
+/// Block sounds, keyed by the block and what happened to it.
+/// Only sounds set in the registry are in here, the others fall back to [`SfxKey`] steps.
+impl AssetKey for (BlockId, BlockSound) {
+    type Asset = AudioSource;
+}
+
+/// Hex map icons, keyed by the kind of cell they represent.
+impl AssetKey for HexagonType {
+    type Asset = Image;
+}
+
 pub trait AssetKey: Sized {
     type Asset: Asset;
 }
@@ -120,8 +177,16 @@ where
 }
 
 impl<K: AssetKey> HandleMap<K> {
-    pub fn all_loaded(&self, asset_server: &AssetServer) -> bool {
-        self.values()
-            .all(|x| asset_server.is_loaded_with_dependencies(x))
+    /// Returns how many assets are done loading, along with the total amount of assets.
+    /// Assets that failed to load count as done so they don't hold up the game forever.
+    pub fn progress(&self, asset_server: &AssetServer) -> (usize, usize) {
+        let done = self
+            .values()
+            .filter(|x| {
+                asset_server.is_loaded_with_dependencies(*x)
+                    || matches!(asset_server.get_load_state(*x), Some(LoadState::Failed(_)))
+            })
+            .count();
+        (done, self.len())
     }
 }
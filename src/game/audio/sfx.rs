@@ -1,7 +1,13 @@
 use bevy::{audio::PlaybackMode, prelude::*};
 use rand::seq::SliceRandom;
 
-use crate::game::assets::{HandleMap, SfxKey};
+use crate::{
+    game::{
+        assets::{HandleMap, SfxKey},
+        settings::Settings,
+    },
+    screen::voxel_world::registry::{BlockId, BlockSound},
+};
 
 pub(super) fn plugin(app: &mut App) {
     app.observe(play_sfx);
@@ -11,18 +17,39 @@ fn play_sfx(
     trigger: Trigger<PlaySfx>,
     mut commands: Commands,
     sfx_handles: Res<HandleMap<SfxKey>>,
+    block_sounds: Option<Res<HandleMap<(BlockId, BlockSound)>>>,
+    settings: Res<Settings>,
 ) {
-    let sfx_key = match trigger.event() {
-        PlaySfx::Key(key) => *key,
-        PlaySfx::RandomStep => random_step(),
+    let (source, position) = match trigger.event() {
+        PlaySfx::Key(key) => (sfx_handles[key].clone_weak(), None),
+        PlaySfx::RandomStep => (sfx_handles[&random_step()].clone_weak(), None),
+        PlaySfx::Block {
+            block,
+            sound,
+            position,
+        } => {
+            let source = block_sounds
+                .as_ref()
+                .and_then(|sounds| sounds.get(&(*block, *sound)))
+                .unwrap_or_else(|| &sfx_handles[&random_step()])
+                .clone_weak();
+            (source, Some(*position))
+        }
     };
-    commands.spawn(AudioSourceBundle {
-        source: sfx_handles[&sfx_key].clone_weak(),
+    let mut entity = commands.spawn(AudioSourceBundle {
+        source,
         settings: PlaybackSettings {
             mode: PlaybackMode::Despawn,
+            volume: settings.sfx_volume(),
+            spatial: position.is_some(),
             ..default()
         },
     });
+    if let Some(position) = position {
+        entity.insert(TransformBundle::from_transform(
+            Transform::from_translation(position),
+        ));
+    }
 }
 
 /// Trigger this event to play a single sound effect.
@@ -30,6 +57,12 @@ fn play_sfx(
 pub enum PlaySfx {
     Key(SfxKey),
     RandomStep,
+    /// A block sound, played from `position` in the voxel world.
+    Block {
+        block: BlockId,
+        sound: BlockSound,
+        position: Vec3,
+    },
 }
 
 fn random_step() -> SfxKey {
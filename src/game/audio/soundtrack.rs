@@ -1,6 +1,9 @@
 use bevy::{audio::PlaybackMode, prelude::*};
 
-use crate::game::assets::{HandleMap, SoundtrackKey};
+use crate::game::{
+    assets::{HandleMap, SoundtrackKey},
+    settings::Settings,
+};
 
 pub(super) fn plugin(app: &mut App) {
     app.register_type::<IsSoundtrack>();
@@ -12,6 +15,7 @@ fn play_soundtrack(
     mut commands: Commands,
     soundtrack_handles: Res<HandleMap<SoundtrackKey>>,
     soundtrack_query: Query<Entity, With<IsSoundtrack>>,
+    settings: Res<Settings>,
 ) {
     for entity in &soundtrack_query {
         commands.entity(entity).despawn_recursive();
@@ -26,6 +30,7 @@ fn play_soundtrack(
             source: soundtrack_handles[&soundtrack_key].clone_weak(),
             settings: PlaybackSettings {
                 mode: PlaybackMode::Loop,
+                volume: settings.music_volume(),
                 ..default()
             },
         },
@@ -45,4 +50,4 @@ pub enum PlaySoundtrack {
 /// Marker component for the soundtrack entity so we can find it later.
 #[derive(Component, Reflect)]
 #[reflect(Component)]
-struct IsSoundtrack;
+pub struct IsSoundtrack;
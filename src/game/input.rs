@@ -0,0 +1,325 @@
+//! Player actions and the inputs bound to them.
+//! Gameplay systems ask whether an [`Action`] is pressed through [`Actions`]
+//! instead of reading keys directly, so every binding can be changed by the player.
+
+use std::fmt;
+
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use super::settings::Settings;
+
+/// Something the player can do, independently of the input used to do it.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize, strum_macros::EnumIter,
+)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Sprint,
+    Crouch,
+    /// Enter the selected cell.
+    Interact,
+    /// Leave the current screen or menu.
+    Back,
+    /// Harvest a block from the selected cell.
+    Harvest,
+    BuildExtractor,
+    Mine,
+    Place,
+    NextSlot,
+    PreviousSlot,
+    ToggleCursor,
+    ToggleFlight,
+    ToggleNoclip,
+    /// Pick the interior of another cell to place as a voxel, in creative.
+    PickVoxel,
+    /// Take the items out of the machine the player is looking at.
+    TakeItems,
+    /// Show the next overlay on the hex map.
+    CycleOverlay,
+}
+
+impl Action {
+    /// Name shown to the player.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveForward => "Move forward",
+            Action::MoveBackward => "Move backward",
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::Jump => "Jump",
+            Action::Sprint => "Sprint",
+            Action::Crouch => "Crouch",
+            Action::Interact => "Interact",
+            Action::Back => "Back",
+            Action::Harvest => "Harvest",
+            Action::BuildExtractor => "Build extractor",
+            Action::Mine => "Mine",
+            Action::Place => "Place",
+            Action::NextSlot => "Next slot",
+            Action::PreviousSlot => "Previous slot",
+            Action::ToggleCursor => "Toggle cursor",
+            Action::ToggleFlight => "Toggle flight",
+            Action::ToggleNoclip => "Toggle noclip",
+            Action::PickVoxel => "Pick voxel",
+            Action::TakeItems => "Take items",
+            Action::CycleOverlay => "Cycle overlay",
+        }
+    }
+
+    fn default_bindings(self) -> Vec<Binding> {
+        use Binding::{Gamepad as Pad, Key, Mouse};
+        match self {
+            Action::MoveForward => vec![
+                Key(KeyCode::KeyW),
+                Key(KeyCode::ArrowUp),
+                Pad(GamepadButtonType::DPadUp),
+            ],
+            Action::MoveBackward => vec![
+                Key(KeyCode::KeyS),
+                Key(KeyCode::ArrowDown),
+                Pad(GamepadButtonType::DPadDown),
+            ],
+            Action::MoveLeft => vec![
+                Key(KeyCode::KeyA),
+                Key(KeyCode::ArrowLeft),
+                Pad(GamepadButtonType::DPadLeft),
+            ],
+            Action::MoveRight => vec![
+                Key(KeyCode::KeyD),
+                Key(KeyCode::ArrowRight),
+                Pad(GamepadButtonType::DPadRight),
+            ],
+            Action::Jump => vec![Key(KeyCode::Space), Pad(GamepadButtonType::South)],
+            Action::Sprint => vec![Key(KeyCode::ShiftLeft), Pad(GamepadButtonType::LeftThumb)],
+            Action::Crouch => vec![
+                Key(KeyCode::ControlLeft),
+                Pad(GamepadButtonType::RightThumb),
+            ],
+            Action::Interact => vec![Key(KeyCode::Enter), Pad(GamepadButtonType::South)],
+            Action::Back => vec![Key(KeyCode::Escape), Pad(GamepadButtonType::East)],
+            Action::Harvest => vec![Key(KeyCode::KeyE), Pad(GamepadButtonType::West)],
+            Action::BuildExtractor => vec![Key(KeyCode::KeyX), Pad(GamepadButtonType::North)],
+            Action::Mine => vec![
+                Mouse(MouseButton::Left),
+                Pad(GamepadButtonType::RightTrigger2),
+            ],
+            Action::Place => vec![
+                Mouse(MouseButton::Right),
+                Pad(GamepadButtonType::LeftTrigger2),
+            ],
+            Action::NextSlot => vec![Key(KeyCode::KeyE), Pad(GamepadButtonType::RightTrigger)],
+            Action::PreviousSlot => vec![Key(KeyCode::KeyQ), Pad(GamepadButtonType::LeftTrigger)],
+            Action::ToggleCursor => vec![Key(KeyCode::Backquote)],
+            Action::ToggleFlight => vec![Key(KeyCode::KeyF), Pad(GamepadButtonType::North)],
+            Action::ToggleNoclip => vec![Key(KeyCode::KeyN)],
+            Action::PickVoxel => vec![Key(KeyCode::KeyV)],
+            Action::TakeItems => vec![Key(KeyCode::KeyR), Pad(GamepadButtonType::West)],
+            Action::CycleOverlay => vec![Key(KeyCode::KeyO), Pad(GamepadButtonType::Select)],
+        }
+    }
+}
+
+/// A single input that can trigger an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType),
+}
+
+impl Binding {
+    /// Keyboard and mouse bindings are used together, gamepad bindings on their own.
+    fn is_gamepad(self) -> bool {
+        matches!(self, Binding::Gamepad(_))
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{key:?}"),
+            Binding::Mouse(button) => write!(f, "Mouse {button:?}"),
+            Binding::Gamepad(button) => write!(f, "Pad {button:?}"),
+        }
+    }
+}
+
+/// The inputs bound to every action.
+#[derive(Reflect, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputMap(HashMap<Action, Vec<Binding>>);
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self(
+            Action::iter()
+                .map(|action| (action, action.default_bindings()))
+                .collect(),
+        )
+    }
+}
+
+impl InputMap {
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.0.get(&action).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Binds `binding` to `action` in place of the previous binding of the same kind of device.
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.0.entry(action).or_default();
+        bindings.retain(|other| other.is_gamepad() != binding.is_gamepad());
+        bindings.insert(0, binding);
+    }
+
+    /// Gives actions added since the map was saved their default bindings.
+    pub fn add_missing_actions(&mut self) {
+        for action in Action::iter() {
+            self.0
+                .entry(action)
+                .or_insert_with(|| action.default_bindings());
+        }
+    }
+}
+
+/// The buttons of every input device, regardless of what they are bound to.
+#[derive(SystemParam)]
+pub struct Inputs<'w> {
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    mouse: Res<'w, ButtonInput<MouseButton>>,
+    gamepad_buttons: Res<'w, ButtonInput<GamepadButton>>,
+    gamepad_axes: Res<'w, Axis<GamepadAxis>>,
+    gamepads: Res<'w, Gamepads>,
+}
+
+impl Inputs<'_> {
+    /// Position of a stick, combined over every gamepad.
+    pub fn stick(&self, x: GamepadAxisType, y: GamepadAxisType) -> Vec2 {
+        self.gamepads
+            .iter()
+            .map(|gamepad| {
+                let axis = |axis_type| {
+                    self.gamepad_axes
+                        .get(GamepadAxis::new(gamepad, axis_type))
+                        .unwrap_or(0.)
+                };
+                Vec2::new(axis(x), axis(y))
+            })
+            .sum::<Vec2>()
+            .clamp_length_max(1.)
+    }
+
+    pub fn pressed(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Key(key) => self.keys.pressed(key),
+            Binding::Mouse(button) => self.mouse.pressed(button),
+            Binding::Gamepad(button) => self.gamepads.iter().any(|gamepad| {
+                self.gamepad_buttons
+                    .pressed(GamepadButton::new(gamepad, button))
+            }),
+        }
+    }
+
+    pub fn just_pressed(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Key(key) => self.keys.just_pressed(key),
+            Binding::Mouse(button) => self.mouse.just_pressed(button),
+            Binding::Gamepad(button) => self.gamepads.iter().any(|gamepad| {
+                self.gamepad_buttons
+                    .just_pressed(GamepadButton::new(gamepad, button))
+            }),
+        }
+    }
+
+    /// The first input pressed this frame on any device, used to pick a new binding.
+    pub fn any_just_pressed(&self) -> Option<Binding> {
+        self.keys
+            .get_just_pressed()
+            .next()
+            .map(|key| Binding::Key(*key))
+            .or_else(|| {
+                self.mouse
+                    .get_just_pressed()
+                    .next()
+                    .map(|button| Binding::Mouse(*button))
+            })
+            .or_else(|| {
+                self.gamepad_buttons
+                    .get_just_pressed()
+                    .next()
+                    .map(|button| Binding::Gamepad(button.button_type))
+            })
+    }
+}
+
+/// Reads the state of actions through the bindings in the [`Settings`].
+#[derive(SystemParam)]
+pub struct Actions<'w> {
+    settings: Res<'w, Settings>,
+    inputs: Inputs<'w>,
+}
+
+impl Actions<'_> {
+    /// Whether any input bound to `action` is held down.
+    pub fn pressed(&self, action: Action) -> bool {
+        self.settings
+            .bindings
+            .bindings(action)
+            .iter()
+            .any(|binding| self.inputs.pressed(*binding))
+    }
+
+    /// Whether any input bound to `action` started being pressed this frame.
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.settings
+            .bindings
+            .bindings(action)
+            .iter()
+            .any(|binding| self.inputs.just_pressed(*binding))
+    }
+
+    /// Whether a key bound to `action` started being pressed this frame. Other devices are ignored.
+    pub fn key_just_pressed(&self, action: Action) -> bool {
+        self.settings
+            .bindings
+            .bindings(action)
+            .iter()
+            .any(|binding| matches!(binding, Binding::Key(_)) && self.inputs.just_pressed(*binding))
+    }
+
+    /// Movement intent from the movement actions and the left stick, with a length of at most 1.
+    /// `y` points forward.
+    pub fn movement(&self) -> Vec2 {
+        let mut intent = self
+            .inputs
+            .stick(GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY);
+        if self.pressed(Action::MoveForward) {
+            intent.y += 1.;
+        }
+        if self.pressed(Action::MoveBackward) {
+            intent.y -= 1.;
+        }
+        if self.pressed(Action::MoveLeft) {
+            intent.x -= 1.;
+        }
+        if self.pressed(Action::MoveRight) {
+            intent.x += 1.;
+        }
+        intent.clamp_length_max(1.)
+    }
+
+    /// Camera movement from the right stick. `y` points up.
+    pub fn look(&self) -> Vec2 {
+        self.inputs
+            .stick(GamepadAxisType::RightStickX, GamepadAxisType::RightStickY)
+    }
+}
+
+/// Run condition that is true on the frame `action` starts being pressed.
+pub fn action_just_pressed(action: Action) -> impl FnMut(Actions) -> bool + Clone {
+    move |actions: Actions| actions.just_pressed(action)
+}
@@ -5,7 +5,11 @@ use bevy::prelude::*;
 mod animation;
 pub mod assets;
 pub mod audio;
+pub mod input;
+pub mod mode;
 mod movement;
+pub mod save;
+pub mod settings;
 pub mod spawn;
 
 pub(super) fn plugin(app: &mut App) {
@@ -13,7 +17,10 @@ pub(super) fn plugin(app: &mut App) {
         animation::plugin,
         audio::plugin,
         assets::plugin,
+        mode::plugin,
         movement::plugin,
+        save::plugin,
+        settings::plugin,
         spawn::plugin,
     ));
 }
@@ -0,0 +1,32 @@
+//! Game modes, chosen when a world is created and stored in its save.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<GameMode>();
+    app.init_resource::<GameMode>();
+}
+
+#[derive(Resource, Reflect, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[reflect(Resource)]
+pub enum GameMode {
+    #[default]
+    Survival,
+    /// Building without limits, with flight.
+    Creative,
+}
+
+/// Run condition that is true while playing in `mode`.
+pub fn in_mode(mode: GameMode) -> impl FnMut(Res<GameMode>) -> bool + Clone {
+    move |current: Res<GameMode>| *current == mode
+}
+
+impl GameMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Survival => "Survival",
+            GameMode::Creative => "Creative",
+        }
+    }
+}
@@ -5,6 +5,7 @@
 
 use bevy::{prelude::*, window::PrimaryWindow};
 
+use super::input::Actions;
 use crate::AppSet;
 
 pub(super) fn plugin(app: &mut App) {
@@ -30,27 +31,13 @@ pub(super) fn plugin(app: &mut App) {
 pub struct MovementController(pub Vec2);
 
 fn record_movement_controller(
-    input: Res<ButtonInput<KeyCode>>,
+    actions: Actions,
     mut controller_query: Query<&mut MovementController>,
 ) {
-    // Collect directional input.
-    let mut intent = Vec2::ZERO;
-    if input.pressed(KeyCode::KeyW) || input.pressed(KeyCode::ArrowUp) {
-        intent.y += 1.0;
-    }
-    if input.pressed(KeyCode::KeyS) || input.pressed(KeyCode::ArrowDown) {
-        intent.y -= 1.0;
-    }
-    if input.pressed(KeyCode::KeyA) || input.pressed(KeyCode::ArrowLeft) {
-        intent.x -= 1.0;
-    }
-    if input.pressed(KeyCode::KeyD) || input.pressed(KeyCode::ArrowRight) {
-        intent.x += 1.0;
-    }
-
-    // Normalize so that diagonal movement has the same speed as
-    // horizontal and vertical movement.
-    let intent = intent.normalize_or_zero();
+    // Collect directional input. Keys are normalized so that diagonal movement
+    // has the same speed as horizontal and vertical movement,
+    // while sticks keep their analog magnitude.
+    let intent = actions.movement();
 
     // Apply movement intent to controllers.
     for mut controller in &mut controller_query {
@@ -0,0 +1,167 @@
+//! Saving the world to disk and loading it back.
+//! Only the state that outlives a screen is saved, since everything else is rebuilt from it.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::mode::GameMode;
+use crate::screen::{
+    hex_map::{exploration::Exploration, extraction::Extractors},
+    voxel_world::{
+        interiors::Interiors, inventory::Inventory, machines::Machines, registry::BlockRegistry,
+    },
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.observe(save_game);
+    app.observe(load_game);
+}
+
+/// Where the world is saved, relative to the working directory.
+#[cfg(not(target_family = "wasm"))]
+const SAVE_PATH: &str = "save.ron";
+
+/// Names and amounts of the blocks new survival worlds start with.
+const STARTING_ITEMS: &[(&str, u32)] = &[("stone_pickaxe", 1)];
+
+/// Trigger this event to write the world to disk.
+#[derive(Event)]
+pub struct SaveGame;
+
+/// Trigger this event to replace the world.
+#[derive(Event)]
+pub enum LoadGame {
+    /// Start over with a freshly generated world.
+    New(GameMode),
+    /// Continue from the last save.
+    Saved,
+}
+
+/// Everything stored in a save.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct SaveData {
+    mode: GameMode,
+    interiors: Interiors,
+    inventory: Inventory,
+    extractors: Extractors,
+    machines: Machines,
+    exploration: Exploration,
+}
+
+impl SaveData {
+    /// Drops everything referring to blocks missing from `registry`, like blocks saved with an
+    /// older or modded registry file, so they can't be looked up later.
+    fn forget_unknown_blocks(&mut self, registry: &BlockRegistry) {
+        let forgotten = self.interiors.forget_unknown_blocks(registry)
+            + self.inventory.forget_unknown_blocks(registry)
+            + self.extractors.forget_unknown_blocks(registry)
+            + self.machines.forget_unknown_blocks(registry);
+        if forgotten > 0 {
+            warn!("Removed {forgotten} blocks missing from the registry from the save");
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn read() -> Option<Self> {
+        let file = std::fs::read_to_string(SAVE_PATH).ok()?;
+        ron::from_str(&file)
+            .inspect_err(|error| error!("Could not parse {SAVE_PATH}: {error}"))
+            .ok()
+    }
+
+    /// There is no file system on the web, so there is never anything to continue from.
+    #[cfg(target_family = "wasm")]
+    fn read() -> Option<Self> {
+        None
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn write(&self) {
+        // Interiors are large, so the file isn't pretty printed.
+        let result = ron::to_string(self)
+            .map_err(|error| error.to_string())
+            .and_then(|file| std::fs::write(SAVE_PATH, file).map_err(|error| error.to_string()));
+        if let Err(error) = result {
+            error!("Could not save the game to {SAVE_PATH}: {error}");
+        }
+    }
+
+    #[cfg(target_family = "wasm")]
+    fn write(&self) {}
+}
+
+/// Whether there is a save to continue from.
+#[cfg(not(target_family = "wasm"))]
+pub fn save_exists() -> bool {
+    std::path::Path::new(SAVE_PATH).exists()
+}
+
+#[cfg(target_family = "wasm")]
+pub fn save_exists() -> bool {
+    false
+}
+
+fn save_game(
+    _trigger: Trigger<SaveGame>,
+    mode: Res<GameMode>,
+    interiors: Res<Interiors>,
+    inventory: Res<Inventory>,
+    extractors: Res<Extractors>,
+    machines: Res<Machines>,
+    exploration: Res<Exploration>,
+) {
+    SaveData {
+        mode: *mode,
+        interiors: interiors.clone(),
+        inventory: inventory.clone(),
+        extractors: extractors.clone(),
+        machines: machines.clone(),
+        exploration: exploration.clone(),
+    }
+    .write();
+}
+
+fn load_game(
+    trigger: Trigger<LoadGame>,
+    mut commands: Commands,
+    registry: Option<Res<BlockRegistry>>,
+) {
+    let data = match trigger.event() {
+        LoadGame::New(mode) => {
+            let mut data = SaveData {
+                mode: *mode,
+                ..default()
+            };
+            if *mode == GameMode::Survival {
+                for (name, amount) in STARTING_ITEMS {
+                    let Some(registry) = &registry else {
+                        warn!("Starting item {name} is missing from the registry");
+                        continue;
+                    };
+                    match registry.block(name) {
+                        Some(block) => {
+                            data.inventory.add_resource(block, *amount, registry);
+                        }
+                        None => warn!("Starting item {name} is missing from the registry"),
+                    }
+                }
+            }
+            data
+        }
+        LoadGame::Saved => {
+            let mut data = SaveData::read().unwrap_or_default();
+            match &registry {
+                Some(registry) => data.forget_unknown_blocks(registry),
+                None => warn!("Loaded a save without a block registry to check it against"),
+            }
+            data
+        }
+    };
+    commands.insert_resource(data.mode);
+    commands.insert_resource(data.interiors);
+    commands.insert_resource(data.inventory);
+    commands.insert_resource(data.extractors);
+    commands.insert_resource(data.machines);
+    commands.insert_resource(data.exploration);
+}
@@ -0,0 +1,104 @@
+//! Player preferences, stored on disk and applied on startup.
+
+use bevy::{audio::Volume, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use super::{audio::soundtrack::IsSoundtrack, input::InputMap};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<Settings>();
+    app.insert_resource(Settings::load());
+    app.add_systems(Update, apply_volume.run_if(resource_changed::<Settings>));
+}
+
+/// Where the settings are stored, relative to the working directory.
+#[cfg(not(target_family = "wasm"))]
+const SETTINGS_PATH: &str = "settings.ron";
+
+#[derive(Resource, Reflect, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct Settings {
+    /// Volume of every sound, multiplied with the music and sound effect volumes.
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    /// Multiplier of the base mouse sensitivity.
+    pub mouse_sensitivity: f32,
+    /// Vertical field of view of the voxel world camera, in degrees.
+    pub fov: f32,
+    pub invert_y: bool,
+    pub bindings: InputMap,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            master_volume: 0.3,
+            music_volume: 1.,
+            sfx_volume: 1.,
+            mouse_sensitivity: 1.,
+            fov: 45.,
+            invert_y: false,
+            bindings: InputMap::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Reads the settings from disk, falling back to the defaults if there are none yet.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn load() -> Self {
+        let Ok(file) = std::fs::read_to_string(SETTINGS_PATH) else {
+            return Self::default();
+        };
+        let mut settings: Self = ron::from_str(&file).unwrap_or_else(|error| {
+            warn!("Could not parse {SETTINGS_PATH}, using default settings: {error}");
+            Self::default()
+        });
+        settings.bindings.add_missing_actions();
+        settings
+    }
+
+    /// There is no file system on the web, so settings only last for the session.
+    #[cfg(target_family = "wasm")]
+    pub fn load() -> Self {
+        Self::default()
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn save(&self) {
+        let result = ron::ser::to_string_pretty(self, default())
+            .map_err(|error| error.to_string())
+            .and_then(|file| {
+                std::fs::write(SETTINGS_PATH, file).map_err(|error| error.to_string())
+            });
+        if let Err(error) = result {
+            error!("Could not save settings to {SETTINGS_PATH}: {error}");
+        }
+    }
+
+    #[cfg(target_family = "wasm")]
+    pub fn save(&self) {}
+
+    pub fn music_volume(&self) -> Volume {
+        Volume::new(self.music_volume)
+    }
+
+    pub fn sfx_volume(&self) -> Volume {
+        Volume::new(self.sfx_volume)
+    }
+}
+
+/// The global volume only applies to sounds started after it changes,
+/// so the soundtrack that is already playing is updated by hand.
+fn apply_volume(
+    settings: Res<Settings>,
+    mut global_volume: ResMut<GlobalVolume>,
+    soundtracks: Query<&AudioSink, With<IsSoundtrack>>,
+) {
+    global_volume.volume = Volume::new(settings.master_volume);
+    for sink in &soundtracks {
+        sink.set_volume(settings.master_volume * settings.music_volume);
+    }
+}
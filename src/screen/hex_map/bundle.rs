@@ -19,6 +19,8 @@ pub struct HexCellBundle {
     pub sprite: Sprite,
     /// A reference-counted handle to the image asset to be drawn.
     pub texture: Handle<Image>,
+    /// The part of the [hex map atlas](super::tiles::HexTiles) drawn for this cell.
+    pub atlas: TextureAtlas,
 }
 
 impl Default for HexCellBundle {
@@ -35,6 +37,7 @@ impl Default for HexCellBundle {
                 ..Default::default()
             },
             texture: Default::default(),
+            atlas: Default::default(),
         }
     }
 }
@@ -0,0 +1,256 @@
+//! Cube coordinate math on [`HexId`], following <https://www.redblobgames.com/grids/hexagons/>.
+
+use bevy::math::IVec2;
+use strum::IntoEnumIterator;
+
+use super::{HexId, RingIter, SpiralIter, WithOffset};
+use crate::screen::MapDirection;
+
+/// Offsets to the six cells sharing a corner but no edge with a cell.
+const DIAGONALS: [HexId; 6] = [
+    HexId::new(2, -1),
+    HexId::new(1, 1),
+    HexId::new(-1, 2),
+    HexId::new(-2, 1),
+    HexId::new(-1, -1),
+    HexId::new(1, -2),
+];
+
+impl HexId {
+    /// Number of steps between this cell and the origin.
+    pub fn length(&self) -> u32 {
+        ((self.q().abs() + self.r().abs() + self.s().abs()) / 2) as u32
+    }
+
+    /// Number of steps between two cells.
+    pub fn distance(&self, other: HexId) -> u32 {
+        (*self - other).length()
+    }
+
+    /// The six cells sharing an edge with this one, clockwise from the top one.
+    pub fn neighbors(&self) -> impl Iterator<Item = HexId> {
+        let center = *self;
+        MapDirection::iter().map(move |direction| center + direction)
+    }
+
+    /// The six cells sharing only a corner with this one.
+    pub fn diagonals(&self) -> impl Iterator<Item = HexId> {
+        let center = *self;
+        DIAGONALS.into_iter().map(move |offset| center + offset)
+    }
+
+    /// This cell rotated 60° counterclockwise on the map around `center`.
+    pub fn rotate_ccw(&self, center: HexId) -> HexId {
+        let offset = *self - center;
+        center + HexId::new(-offset.r(), -offset.s())
+    }
+
+    /// This cell rotated 60° clockwise on the map around `center`.
+    pub fn rotate_cw(&self, center: HexId) -> HexId {
+        let offset = *self - center;
+        center + HexId::new(-offset.s(), -offset.q())
+    }
+
+    /// This cell mirrored across the `q` axis through `center`, swapping `r` and `s`.
+    pub fn reflect_q(&self, center: HexId) -> HexId {
+        let offset = *self - center;
+        center + HexId::new(offset.q(), offset.s())
+    }
+
+    /// This cell mirrored across the `r` axis through `center`, swapping `q` and `s`.
+    pub fn reflect_r(&self, center: HexId) -> HexId {
+        let offset = *self - center;
+        center + HexId::new(offset.s(), offset.r())
+    }
+
+    /// This cell mirrored across the `s` axis through `center`, swapping `q` and `r`.
+    pub fn reflect_s(&self, center: HexId) -> HexId {
+        let offset = *self - center;
+        center + HexId::new(offset.r(), offset.q())
+    }
+
+    /// The cell a share `t` of the way from this one to `other`.
+    pub fn lerp(&self, other: HexId, t: f32) -> HexId {
+        let q = self.q() as f32 + (other.q() - self.q()) as f32 * t;
+        let r = self.r() as f32 + (other.r() - self.r()) as f32 * t;
+        HexId::round(q, r)
+    }
+
+    /// The cells on a straight line from this one to `other`, both included.
+    /// Each cell is a neighbor of the one before it.
+    pub fn line_to(&self, other: HexId) -> impl Iterator<Item = HexId> {
+        let steps = self.distance(other);
+        // Nudged off the edges between cells so lines along them round the same way every time.
+        let (q, r) = (self.q() as f32 + 1e-3, self.r() as f32 + 2e-3);
+        let (dq, dr) = ((other.q() - self.q()) as f32, (other.r() - self.r()) as f32);
+        (0..=steps).map(move |step| {
+            let t = if steps == 0 {
+                0.
+            } else {
+                step as f32 / steps as f32
+            };
+            HexId::round(q + dq * t, r + dr * t)
+        })
+    }
+
+    /// The cells `radius` steps away from this one.
+    pub fn ring(&self, radius: u32) -> impl Iterator<Item = HexId> {
+        RingIter::new(radius).with_offset(*self)
+    }
+
+    /// The cells up to `radius` steps away from this one, from the inside out.
+    pub fn spiral(&self, radius: u32) -> impl Iterator<Item = HexId> {
+        SpiralIter::new(radius).with_offset(*self)
+    }
+
+    /// Column and row of this cell in an "odd-q" offset grid, where odd columns are shifted up.
+    pub fn to_offset(&self) -> IVec2 {
+        IVec2::new(self.q(), self.r() + (self.q() - (self.q() & 1)) / 2)
+    }
+
+    /// The cell at `offset` in an "odd-q" offset grid, see [`HexId::to_offset`].
+    pub fn from_offset(offset: IVec2) -> HexId {
+        HexId::new(offset.x, offset.y - (offset.x - (offset.x & 1)) / 2)
+    }
+
+    /// Column and row of this cell in a doubled grid, where rows are counted in half cells.
+    pub fn to_doubled(&self) -> IVec2 {
+        IVec2::new(self.q(), 2 * self.r() + self.q())
+    }
+
+    /// The cell at `doubled` in a doubled grid, see [`HexId::to_doubled`].
+    /// Positions between cells, where column and row differ in parity, round down.
+    pub fn from_doubled(doubled: IVec2) -> HexId {
+        HexId::new(doubled.x, (doubled.y - doubled.x).div_euclid(2))
+    }
+}
+
+/// Cells the properties below are checked on.
+#[cfg(test)]
+fn test_cells() -> impl Iterator<Item = HexId> {
+    HexId::new(3, -7).spiral(12)
+}
+
+#[test]
+fn from_xyz_round_trip() {
+    for cell in test_cells() {
+        assert_eq!(HexId::from_xyz(cell.xyz()), cell);
+    }
+}
+
+#[test]
+fn from_xyz_inside_cell() {
+    use super::super::hex_util::{HEX_SPACING, SQR_3_DIV_TWO};
+    use bevy::math::Vec3;
+    use std::f32::consts::TAU;
+
+    // Anywhere within the circle touching the edges of a cell belongs to it.
+    let inner = HEX_SPACING * SQR_3_DIV_TWO * 0.99;
+    for cell in test_cells() {
+        for step in 0..24 {
+            let angle = step as f32 / 24. * TAU;
+            for scale in [0.25, 0.5, 1.] {
+                let offset = Vec3::new(angle.cos(), angle.sin(), 0.) * inner * scale;
+                assert_eq!(HexId::from_xyz(cell.xyz() + offset), cell);
+            }
+        }
+    }
+}
+
+#[test]
+fn arithmetic() {
+    let origin = HexId::new(0, 0);
+    for cell in test_cells() {
+        let other = HexId::new(-2, 5);
+        assert_eq!(cell + other - other, cell);
+        assert_eq!(cell + -cell, origin);
+        assert_eq!(cell * 3, cell + cell + cell);
+        assert_eq!(cell.q() + cell.r() + cell.s(), 0);
+        assert_eq!(cell.distance(other), other.distance(cell));
+        assert_eq!((cell * 2).length(), cell.length() * 2);
+    }
+}
+
+#[test]
+fn neighbors_and_diagonals() {
+    for cell in test_cells() {
+        assert!(cell
+            .neighbors()
+            .all(|neighbor| cell.distance(neighbor) == 1));
+        assert!(cell
+            .diagonals()
+            .all(|diagonal| cell.distance(diagonal) == 2));
+        // Neighbors next to each other in the order touch too.
+        let neighbors: Vec<_> = cell.neighbors().collect();
+        for (index, neighbor) in neighbors.iter().enumerate() {
+            assert_eq!(neighbor.distance(neighbors[(index + 1) % 6]), 1);
+        }
+    }
+}
+
+#[test]
+fn rotation_and_reflection() {
+    let center = HexId::new(1, 2);
+    for cell in test_cells() {
+        let rotated = (0..6).fold(cell, |cell, _| cell.rotate_ccw(center));
+        assert_eq!(rotated, cell);
+        assert_eq!(cell.rotate_ccw(center).rotate_cw(center), cell);
+        assert_eq!(
+            cell.rotate_ccw(center).distance(center),
+            cell.distance(center)
+        );
+        for reflect in [HexId::reflect_q, HexId::reflect_r, HexId::reflect_s] {
+            assert_eq!(reflect(&reflect(&cell, center), center), cell);
+            assert_eq!(
+                reflect(&cell, center).distance(center),
+                cell.distance(center)
+            );
+        }
+    }
+    // Rotating turns each direction into the next one.
+    for direction in MapDirection::iter() {
+        let origin = HexId::new(0, 0);
+        let next = direction.direction().rotate_ccw(origin);
+        assert_eq!(next, direction.next().direction());
+    }
+}
+
+#[test]
+fn lines() {
+    let start = HexId::new(-1, 4);
+    for cell in test_cells() {
+        let line: Vec<_> = start.line_to(cell).collect();
+        assert_eq!(line.len() as u32, start.distance(cell) + 1);
+        assert_eq!(line.first(), Some(&start));
+        assert_eq!(line.last(), Some(&cell));
+        assert!(line.windows(2).all(|pair| pair[0].distance(pair[1]) == 1));
+        assert_eq!(start.lerp(cell, 0.), start);
+        assert_eq!(start.lerp(cell, 1.), cell);
+    }
+}
+
+#[test]
+fn rings_and_spirals() {
+    let center = HexId::new(4, -2);
+    for radius in 0..6 {
+        assert!(center
+            .ring(radius)
+            .all(|cell| cell.distance(center) == radius));
+        assert!(center
+            .spiral(radius)
+            .all(|cell| cell.distance(center) <= radius));
+        assert_eq!(
+            center.spiral(radius).count() as u32,
+            3 * radius * (radius + 1) + 1
+        );
+    }
+}
+
+#[test]
+fn offset_and_doubled_round_trip() {
+    for cell in test_cells() {
+        assert_eq!(HexId::from_offset(cell.to_offset()), cell);
+        assert_eq!(HexId::from_doubled(cell.to_doubled()), cell);
+        assert_eq!(cell.to_doubled().x, cell.to_offset().x);
+    }
+}
@@ -1,23 +1,37 @@
 use bevy::{
-    asset::{AssetServer, Handle},
-    math::{IVec2, Vec3},
-    prelude::{Changed, Component, FromWorld, Query, Resource},
-    render::texture::Image,
+    asset::AssetServer,
+    math::{IVec2, IVec3, Vec3},
+    prelude::{Changed, Color, Commands, Component, Local, Query, Reflect, Res, Sprite},
+    sprite::TextureAtlas,
     transform::components::Transform,
     utils::HashMap,
 };
+use serde::{Deserialize, Serialize};
 use std::f32::consts::PI;
+use strum::IntoEnumIterator;
+mod coords;
 mod iterators;
 mod ops;
 
 pub use iterators::*;
-use strum::IntoEnumIterator;
 
-use crate::screen::MapDirection;
+use crate::game::assets::HandleMap;
+use crate::screen::{
+    voxel_world::{
+        interiors::Interiors,
+        registry::{BlockId, BlockRegistry},
+        BlockType, VoxelData, VOXEL_DIVISION_FACTOR,
+    },
+    MapDirection,
+};
 
-use super::hex_util::{HEX_SIZE, HEX_SPACING, SQR_3, SQR_3_DIV_THREE, SQR_3_DIV_TWO};
+use super::{
+    hex_util::{HEX_SPACING, SQR_3, SQR_3_DIV_THREE, SQR_3_DIV_TWO},
+    tiles::{HexTiles, Tile},
+};
 
-#[derive(Component, PartialEq, Eq, Hash, Debug, Clone, Copy, Default)]
+#[derive(Component, PartialEq, Eq, Hash, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct HexId(IVec2);
 
 impl HexId {
@@ -56,7 +70,7 @@ impl HexId {
             x: self.x(),
             y: self.y(),
             z: 0.,
-        } * HEX_SIZE
+        } * HEX_SPACING
     }
 
     pub fn round(q: f32, r: f32) -> HexId {
@@ -96,8 +110,9 @@ impl HexId {
 
 pub(crate) fn update_transforms(mut hexagons: Query<(&mut Transform, &HexId), Changed<HexId>>) {
     for (mut pos, hex) in &mut hexagons {
-        pos.translation.x = hex.x() * HEX_SPACING;
-        pos.translation.y = hex.y() * HEX_SPACING;
+        let xyz = hex.xyz();
+        pos.translation.x = xyz.x;
+        pos.translation.y = xyz.y;
     }
 }
 
@@ -127,6 +142,23 @@ impl MapDirection {
         }
     }
 
+    /// Normal of the face of a voxel interior that leads to the neighbour in this direction.
+    pub const fn face_normal(&self) -> IVec3 {
+        match self {
+            MapDirection::Down => IVec3::NEG_Y,
+            MapDirection::East => IVec3::Z,
+            MapDirection::North => IVec3::X,
+            MapDirection::Up => IVec3::Y,
+            MapDirection::West => IVec3::NEG_Z,
+            MapDirection::South => IVec3::NEG_X,
+        }
+    }
+
+    /// The direction whose interior face has `normal`, if there is one.
+    pub fn from_face_normal(normal: IVec3) -> Option<MapDirection> {
+        MapDirection::iter().find(|direction| direction.face_normal() == normal)
+    }
+
     pub fn angle(&self) -> f32 {
         match self {
             MapDirection::Down => -PI,
@@ -139,32 +171,137 @@ impl MapDirection {
     }
 }
 
-#[derive(Resource)]
-pub struct CellIcons(HashMap<HexagonType, Handle<Image>>);
+/// Icons of all hexagon types.
+pub type CellIcons = HandleMap<HexagonType>;
 
-impl CellIcons {
-    pub fn get(&self, hex: HexagonType) -> Handle<Image> {
-        self.0.get(&hex).cloned().unwrap_or_default()
-    }
+/// Icon of cells that contain nothing of interest.
+const EMPTY_ICON: &str = "images/hexes/empty.png";
+
+/// Voxel cells don't have an icon of their own yet, so they show the empty one with this tint.
+const VOXEL_TINT: Color = Color::srgb(0.6, 0.8, 1.0);
+
+/// Share of an interior a block has to make up for the cell to be rich in it.
+const RICH_SHARE: f32 = 0.1;
+
+/// Load the icons of all hexagon types. Runs again whenever the block registry is reloaded,
+/// after which the [hex map atlas](HexTiles) is built again.
+pub(crate) fn setup_cell_icons(
+    mut commands: Commands,
+    registry: Res<BlockRegistry>,
+    asset_server: Res<AssetServer>,
+) {
+    let empty = asset_server.load(EMPTY_ICON);
+    let icons = [
+        (HexagonType::Empty, empty.clone()),
+        (HexagonType::Voxel, empty),
+    ]
+    .into_iter()
+    .chain(registry.iter().filter_map(|(id, block)| {
+        let icon = block.hex_icon.clone()?;
+        Some((HexagonType::Block(id), asset_server.load(icon)))
+    }));
+    commands.insert_resource(CellIcons::from(icons.collect::<HashMap<_, _>>()));
+    commands.remove_resource::<HexTiles>();
 }
 
-impl FromWorld for CellIcons {
-    fn from_world(world: &mut bevy::prelude::World) -> Self {
-        let mut icons = CellIcons(HashMap::default());
-        let asset_server = world.resource::<AssetServer>();
-        for hex in HexagonType::iter() {
-            icons.0.insert(
-                hex,
-                asset_server.load(format!("images/hexes/{:?}.png", hex).to_lowercase()),
-            );
+#[derive(Component, PartialEq, Eq, Debug, Hash, Clone, Copy, Reflect, Serialize, Deserialize)]
+pub enum HexagonType {
+    Empty,
+    /// A cell rich in a block that has a hex icon.
+    Block(BlockId),
+    /// A cell holding voxels nested inside its interior.
+    Voxel,
+}
+
+impl HexagonType {
+    /// Every hexagon type the registry knows about.
+    pub fn all(registry: &BlockRegistry) -> impl Iterator<Item = HexagonType> + '_ {
+        std::iter::once(HexagonType::Empty).chain(
+            registry
+                .iter()
+                .filter(|(_, block)| block.hex_icon.is_some())
+                .map(|(id, _)| HexagonType::Block(id)),
+        )
+    }
+
+    /// The block that is extracted from the interior of this kind of cell.
+    pub fn resource(&self) -> Option<BlockType> {
+        match self {
+            HexagonType::Empty | HexagonType::Voxel => None,
+            HexagonType::Block(id) => Some(BlockType::Block(*id)),
+        }
+    }
+
+    /// Share of `interior` made of the resource of this kind of cell.
+    pub fn resource_share(&self, interior: &VoxelData) -> f32 {
+        let Some(resource) = self.resource() else {
+            return 0.;
+        };
+        let count = interior
+            .iter()
+            .filter(|(_, block)| **block == resource)
+            .count();
+        count as f32 / VOXEL_DIVISION_FACTOR.pow(3) as f32
+    }
+
+    /// The kind of cell `interior` makes, as seen from the hex map.
+    /// Interiors holding nested voxels are [`HexagonType::Voxel`].
+    /// Otherwise the cell is rich in the rarest block with a hex icon that still makes up
+    /// a [`RICH_SHARE`] of the interior, so ore stands out from the stone around it.
+    pub fn from_interior(interior: &VoxelData, registry: &BlockRegistry) -> HexagonType {
+        let mut counts = HashMap::<BlockId, usize>::default();
+        for (_, block) in interior.iter() {
+            match block {
+                BlockType::Voxel(_) | BlockType::MultiVoxel(_) => return HexagonType::Voxel,
+                BlockType::Block(id)
+                    if registry
+                        .get(*id)
+                        .is_some_and(|block| block.hex_icon.is_some()) =>
+                {
+                    *counts.entry(*id).or_default() += 1;
+                }
+                _ => {}
+            }
         }
-        icons
+        let rich = (RICH_SHARE * VOXEL_DIVISION_FACTOR.pow(3) as f32).ceil() as usize;
+        counts
+            .into_iter()
+            .filter(|(_, count)| *count >= rich)
+            .min_by_key(|(id, count)| (*count, *id))
+            .map_or(HexagonType::Empty, |(id, _)| HexagonType::Block(id))
     }
 }
 
-#[derive(Component, PartialEq, Eq, Debug, strum_macros::EnumIter, Hash, Clone, Copy)]
-pub enum HexagonType {
-    Empty,
-    Stone,
-    Coal,
+/// Keep the type and icon of every cell in line with what is inside of it.
+/// Cells whose interior was never generated keep the type they were spawned with.
+pub(crate) fn update_cell_types(
+    mut hexes: Query<(&HexId, &mut HexagonType, &mut TextureAtlas, &mut Sprite)>,
+    interiors: Res<Interiors>,
+    registry: Res<BlockRegistry>,
+    tiles: Res<HexTiles>,
+    // Interiors are only classified again when they change.
+    mut known: Local<HashMap<HexId, (VoxelData, HexagonType)>>,
+) {
+    for (id, mut hex_type, mut atlas, mut sprite) in &mut hexes {
+        let Some(interior) = interiors.get(*id) else {
+            continue;
+        };
+        let derived = match known.get(id) {
+            Some((seen, derived)) if seen == interior => *derived,
+            _ => {
+                let derived = HexagonType::from_interior(interior, &registry);
+                known.insert(*id, (interior.clone(), derived));
+                derived
+            }
+        };
+        if *hex_type != derived {
+            *hex_type = derived;
+            *atlas = tiles.atlas(Tile::Cell(derived));
+            sprite.color = if derived == HexagonType::Voxel {
+                VOXEL_TINT
+            } else {
+                Color::WHITE
+            };
+        }
+    }
 }
@@ -1,5 +1,5 @@
 use super::{MapDirection, HexId};
-use core::ops::{Add, AddAssign};
+use core::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
 
 impl Add<MapDirection> for HexId {
     type Output = HexId;
@@ -27,3 +27,44 @@ impl AddAssign<MapDirection> for HexId {
         *self += rhs.direction();
     }
 }
+
+impl Sub<MapDirection> for HexId {
+    type Output = HexId;
+    fn sub(self, rhs: MapDirection) -> Self::Output {
+        self - rhs.direction()
+    }
+}
+
+impl Sub for HexId {
+    type Output = HexId;
+    fn sub(self, rhs: Self) -> Self::Output {
+        HexId::new(self.q() - rhs.q(), self.r() - rhs.r())
+    }
+}
+
+impl SubAssign for HexId {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0.y -= rhs.0.y;
+        self.0.x -= rhs.0.x;
+    }
+}
+
+impl SubAssign<MapDirection> for HexId {
+    fn sub_assign(&mut self, rhs: MapDirection) {
+        *self -= rhs.direction();
+    }
+}
+
+impl Neg for HexId {
+    type Output = HexId;
+    fn neg(self) -> Self::Output {
+        HexId::new(-self.q(), -self.r())
+    }
+}
+
+impl Mul<i32> for HexId {
+    type Output = HexId;
+    fn mul(self, rhs: i32) -> Self::Output {
+        HexId::new(self.q() * rhs, self.r() * rhs)
+    }
+}
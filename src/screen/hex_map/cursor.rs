@@ -3,6 +3,7 @@ pub struct Cursor;
 
 use super::cells::HexId;
 use super::hex_util::HEX_SIZE;
+use super::tiles::{HexTiles, MapLayer, Tile};
 use crate::game::spawn::player::Player;
 use crate::screen::MapDirection;
 use crate::screen::Screen;
@@ -19,7 +20,7 @@ impl Plugin for CursorPlugin {
     }
 }
 
-fn spawn_cursor(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn spawn_cursor(mut commands: Commands, tiles: Res<HexTiles>) {
     commands.spawn((
         MapDirection::Down,
         Name::new("Cursor"),
@@ -30,10 +31,11 @@ fn spawn_cursor(mut commands: Commands, asset_server: Res<AssetServer>) {
                 custom_size: Some(Vec2::splat(HEX_SIZE)),
                 ..Default::default()
             },
-            texture: asset_server.load("images/hexes/outline.png"),
-            transform: Transform::from_translation(Vec3::NEG_Z * -9.),
+            texture: tiles.image(),
+            transform: Transform::from_translation(Vec3::Z * MapLayer::Selection.z()),
             ..Default::default()
         },
+        tiles.atlas(Tile::Outline),
     ));
 }
 
@@ -61,7 +63,7 @@ fn move_cursor(
             .xyz()
             .distance_squared(player.translation);
         for neighbor in MapDirection::iter() {
-            let next = ((id + neighbor).xyz() / 2.).distance_squared(player.translation);
+            let next = (id + neighbor).xyz().distance_squared(player.translation);
             if next < distance {
                 distance = next;
                 direction = neighbor;
@@ -0,0 +1,163 @@
+//! Fog of war over the hex map.
+//! Cells near the player are visible, cells seen before are dimmed and the rest are hidden.
+//! Cells the player has been inside of are marked. Exploration is kept in saves.
+
+use bevy::{prelude::*, utils::HashSet};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    bundle::HexCellBundle,
+    cells::{HexId, HexagonType},
+    tiles::{HexTiles, MapLayer, Tile},
+};
+use crate::{
+    game::spawn::player::Player,
+    screen::{HexSelect, Screen},
+    AppSet,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<Exploration>();
+    app.add_systems(OnEnter(Screen::VoxelWorld), visit_cell);
+    app.add_systems(
+        Update,
+        (
+            explore,
+            spawn_fog_tiles.run_if(resource_exists::<HexTiles>),
+            update_fog_tiles,
+        )
+            .chain()
+            .in_set(AppSet::Update)
+            .run_if(in_state(Screen::HexMap)),
+    );
+}
+
+/// Distance in cells up to which the player sees around them.
+const VISIBILITY_RADIUS: u32 = 3;
+
+/// Fog over cells that were never seen.
+const HIDDEN: Color = Color::srgb(0.05, 0.05, 0.08);
+/// Fog over cells that were seen before but are out of sight.
+const DIMMED: Color = Color::srgba(0.05, 0.05, 0.08, 0.6);
+/// Outline of cells the player has been inside of.
+const VISITED: Color = Color::srgb(0.3, 0.6, 1.0);
+
+/// The cells the player has found so far.
+#[derive(Resource, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Exploration {
+    seen: HashSet<HexId>,
+    visited: HashSet<HexId>,
+}
+
+impl Exploration {
+    /// Whether the cell was ever within sight of the player.
+    pub fn is_seen(&self, id: HexId) -> bool {
+        self.seen.contains(&id)
+    }
+
+    /// Whether the player has been inside of the cell.
+    pub fn is_visited(&self, id: HexId) -> bool {
+        self.visited.contains(&id)
+    }
+}
+
+/// The fog tile of a cell.
+#[derive(Component)]
+struct FogTile;
+
+/// The outline marking a visited cell.
+#[derive(Component)]
+struct VisitedMarker;
+
+fn visit_cell(hex_select: Res<HexSelect>, mut exploration: ResMut<Exploration>) {
+    exploration.seen.insert(hex_select.hex_id);
+    exploration.visited.insert(hex_select.hex_id);
+}
+
+/// The cells within sight of a player standing in `center`.
+fn sight(center: HexId) -> impl Iterator<Item = HexId> {
+    center.spiral(VISIBILITY_RADIUS)
+}
+
+/// Reveal the cells around the player.
+fn explore(player: Query<&Transform, With<Player>>, mut exploration: ResMut<Exploration>) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    let unseen: Vec<_> = sight(HexId::from_xyz(player.translation))
+        .filter(|id| !exploration.is_seen(*id))
+        .collect();
+    // Only touch the resource when something new comes into sight.
+    if !unseen.is_empty() {
+        exploration.seen.extend(unseen);
+    }
+}
+
+/// Give every new cell a fog tile and a visited marker, both hidden until needed.
+fn spawn_fog_tiles(
+    mut commands: Commands,
+    cells: Query<&HexId, Added<HexagonType>>,
+    tiles: Res<HexTiles>,
+) {
+    for id in &cells {
+        commands.spawn((
+            Name::new("Fog Tile"),
+            FogTile,
+            StateScoped(Screen::HexMap),
+            HexCellBundle {
+                id: *id,
+                transform: Transform::from_translation(Vec3::Z * MapLayer::Fog.z()),
+                texture: tiles.image(),
+                atlas: tiles.atlas(Tile::Fill),
+                ..default()
+            },
+        ));
+        commands.spawn((
+            Name::new("Visited Marker"),
+            VisitedMarker,
+            StateScoped(Screen::HexMap),
+            HexCellBundle {
+                id: *id,
+                transform: Transform::from_translation(Vec3::Z * MapLayer::Structures.z()),
+                texture: tiles.image(),
+                atlas: tiles.atlas(Tile::Outline),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn update_fog_tiles(
+    player: Query<&Transform, With<Player>>,
+    exploration: Res<Exploration>,
+    mut fog: Query<(&HexId, &mut Sprite), (With<FogTile>, Without<VisitedMarker>)>,
+    mut markers: Query<(&HexId, &mut Sprite), With<VisitedMarker>>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    let visible: HashSet<HexId> = sight(HexId::from_xyz(player.translation)).collect();
+    for (id, mut sprite) in &mut fog {
+        let color = if visible.contains(id) {
+            Color::NONE
+        } else if exploration.is_seen(*id) {
+            DIMMED
+        } else {
+            HIDDEN
+        };
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+    for (id, mut sprite) in &mut markers {
+        let color = if exploration.is_visited(*id) {
+            VISITED
+        } else {
+            Color::NONE
+        };
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! Resource extraction on the hex map.
+//! Harvesting takes a single block out of the selected cell's interior,
+//! while extractors keep pulling blocks out of it over time until it is depleted.
+
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    bundle::HexCellBundle,
+    cells::{HexId, HexagonType},
+    cursor::Cursor,
+    hex_util::{cell_type, HEX_SIZE},
+    tiles::{HexTiles, MapLayer, Tile},
+};
+use crate::{
+    game::{
+        input::{Action, Actions},
+        mode::GameMode,
+    },
+    screen::{
+        voxel_world::{
+            interiors::Interiors, inventory::Inventory, registry::BlockRegistry, BlockType,
+            VoxelData,
+        },
+        Screen,
+    },
+    AppSet,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<Extractors>();
+
+    // Extractors keep working while the player is inside a cell.
+    app.add_systems(
+        Update,
+        (
+            tick_extractors.in_set(AppSet::TickTimers),
+            run_extractors
+                .in_set(AppSet::Update)
+                .run_if(resource_exists::<BlockRegistry>),
+        ),
+    );
+
+    app.add_systems(
+        Update,
+        (
+            harvest_cell,
+            build_extractor,
+            show_extractors.run_if(resource_exists::<HexTiles>),
+        )
+            .in_set(AppSet::Update)
+            .run_if(in_state(Screen::HexMap)),
+    );
+}
+
+/// How often an extractor pulls a block out of its cell.
+const EXTRACTOR_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Names and amounts of the blocks needed to build an extractor.
+const EXTRACTOR_COST: &[(&str, u32)] = &[("stone", 4)];
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Extractor {
+    timer: Timer,
+    hex_type: HexagonType,
+    resource: BlockType,
+}
+
+/// All extractors on the hex map, keyed by the cell they are built on.
+/// This is a resource rather than a component because cells are despawned when leaving the hex map.
+#[derive(Resource, Default, Clone, Serialize, Deserialize)]
+pub struct Extractors(HashMap<HexId, Extractor>);
+
+impl Extractors {
+    /// Removes the extractors of blocks missing from `registry`, returning how many there were.
+    pub fn forget_unknown_blocks(&mut self, registry: &BlockRegistry) -> usize {
+        let before = self.0.len();
+        self.0
+            .retain(|_, extractor| registry.knows(&extractor.resource));
+        before - self.0.len()
+    }
+}
+
+fn harvest_cell(
+    actions: Actions,
+    cursor: Query<&HexId, With<Cursor>>,
+    hexes: Query<(&HexId, &HexagonType)>,
+    mut interiors: ResMut<Interiors>,
+    mut inventory: ResMut<Inventory>,
+    registry: Res<BlockRegistry>,
+) {
+    if !actions.just_pressed(Action::Harvest) {
+        return;
+    }
+    let id = *cursor.single();
+    let hex_type = cell_type(&hexes, id);
+    let Some(resource) = hex_type.resource() else {
+        return;
+    };
+    let interior = interiors.get_or_generate(id, hex_type, &registry);
+    if let Some(pos) = find_resource(interior, &resource) {
+        if inventory.add_resource(resource, 1, &registry) {
+            interior.set(pos, BlockType::Air);
+        }
+    }
+}
+
+fn build_extractor(
+    actions: Actions,
+    cursor: Query<&HexId, With<Cursor>>,
+    hexes: Query<(&HexId, &HexagonType)>,
+    mut extractors: ResMut<Extractors>,
+    mut inventory: ResMut<Inventory>,
+    registry: Res<BlockRegistry>,
+    mode: Res<GameMode>,
+) {
+    if !actions.just_pressed(Action::BuildExtractor) {
+        return;
+    }
+    let id = *cursor.single();
+    let hex_type = cell_type(&hexes, id);
+    let Some(resource) = hex_type.resource() else {
+        return;
+    };
+    let Some(cost) = EXTRACTOR_COST
+        .iter()
+        .map(|(name, amount)| Some((registry.block(name)?, *amount)))
+        .collect::<Option<Vec<_>>>()
+    else {
+        warn!("Extractor cost refers to a block missing from the registry");
+        return;
+    };
+    if extractors.0.contains_key(&id) {
+        return;
+    }
+    // Building is free in creative.
+    if *mode == GameMode::Survival && !inventory.check_and_deduct_resources(&cost) {
+        return;
+    }
+    extractors.0.insert(
+        id,
+        Extractor {
+            timer: Timer::new(EXTRACTOR_INTERVAL, TimerMode::Repeating),
+            hex_type,
+            resource,
+        },
+    );
+}
+
+fn tick_extractors(time: Res<Time>, mut extractors: ResMut<Extractors>) {
+    for extractor in extractors.0.values_mut() {
+        extractor.timer.tick(time.delta());
+    }
+}
+
+fn run_extractors(
+    mut extractors: ResMut<Extractors>,
+    mut interiors: ResMut<Interiors>,
+    mut inventory: ResMut<Inventory>,
+    registry: Res<BlockRegistry>,
+) {
+    for (id, extractor) in &mut extractors.0 {
+        if !extractor.timer.just_finished() {
+            continue;
+        }
+        let interior = interiors.get_or_generate(*id, extractor.hex_type, &registry);
+        if let Some(pos) = find_resource(interior, &extractor.resource) {
+            if inventory.add_resource(extractor.resource.clone(), 1, &registry) {
+                interior.set(pos, BlockType::Air);
+            }
+        }
+    }
+}
+
+/// Marks a cell with an extractor on the hex map.
+#[derive(Component)]
+struct ExtractorMarker;
+
+/// Size of extractor markers, relative to the cell.
+const MARKER_SCALE: f32 = 0.4;
+const MARKER_COLOR: Color = Color::srgb(0.9, 0.6, 0.2);
+
+/// Keep a marker on the structure layer of every cell with an extractor.
+fn show_extractors(
+    mut commands: Commands,
+    extractors: Res<Extractors>,
+    markers: Query<(Entity, &HexId), With<ExtractorMarker>>,
+    tiles: Res<HexTiles>,
+) {
+    let mut unmarked: HashSet<HexId> = extractors.0.keys().copied().collect();
+    for (entity, id) in &markers {
+        if !unmarked.remove(id) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    for id in unmarked {
+        commands.spawn((
+            Name::new("Extractor Marker"),
+            ExtractorMarker,
+            StateScoped(Screen::HexMap),
+            HexCellBundle {
+                id,
+                transform: Transform::from_translation(Vec3::Z * MapLayer::Structures.z())
+                    .with_scale(Vec3::splat(MARKER_SCALE)),
+                sprite: Sprite {
+                    color: MARKER_COLOR,
+                    custom_size: Some(Vec2::splat(HEX_SIZE)),
+                    ..default()
+                },
+                texture: tiles.image(),
+                atlas: tiles.atlas(Tile::Fill),
+                ..default()
+            },
+        ));
+    }
+}
+
+/// Finds the highest block of type `resource`, so cells are dug out from the top down.
+fn find_resource(interior: &VoxelData, resource: &BlockType) -> Option<IVec3> {
+    interior
+        .iter()
+        .filter(|(_, block)| *block == resource)
+        .max_by_key(|(pos, _)| pos.y)
+        .map(|(pos, _)| pos)
+}
@@ -9,26 +9,28 @@ const SEED: [u8; 32] = [
     0b01101100, 0, 0, 0, 0, 0, 0, 0,
 ];
 
-use std::ops::DerefMut;
-
 use bevy::prelude::*;
 use rand::{seq::IteratorRandom, Rng, SeedableRng};
-use strum::IntoEnumIterator;
 // ! Fix test module
-use crate::screen::{
-    hex_map::{
-        bundle::HexCellBundle,
-        cells::{self, CellIcons, HexId, HexagonType},
-        cursor,
+use crate::{
+    game::input::{Action, Actions},
+    screen::{
+        hex_map::{
+            bundle::HexCellBundle,
+            cells::{self, HexId, HexagonType},
+            cursor,
+            tiles::{HexTiles, MapLayer, Tile},
+        },
+        voxel_world::{interiors::Interiors, registry::BlockRegistry},
+        HexSelect, MapDirection, Screen,
     },
-    Direction, HexSelect, MapDirection, Screen,
 };
 
-pub fn spawn_test_grid(mut commands: Commands, icons: Res<CellIcons>) {
+pub fn spawn_test_grid(mut commands: Commands, tiles: Res<HexTiles>, registry: Res<BlockRegistry>) {
     let mut rng = rand::rngs::StdRng::from_seed(SEED);
     for hex_id in cells::SpiralIter::new(10) {
         let hex_type = if rng.gen_bool(0.1) {
-            HexagonType::iter()
+            HexagonType::all(&registry)
                 .choose(&mut rng)
                 .expect("Iter not Empty")
         } else {
@@ -39,8 +41,9 @@ pub fn spawn_test_grid(mut commands: Commands, icons: Res<CellIcons>) {
             hex_type,
             HexCellBundle {
                 id: hex_id,
-                transform: Transform::from_translation(Vec3::NEG_Z * 10.),
-                texture: icons.get(hex_type),
+                transform: Transform::from_translation(Vec3::Z * MapLayer::Terrain.z()),
+                texture: tiles.image(),
+                atlas: tiles.atlas(Tile::Cell(hex_type)),
                 ..Default::default()
             },
         ));
@@ -48,32 +51,37 @@ pub fn spawn_test_grid(mut commands: Commands, icons: Res<CellIcons>) {
 }
 
 pub fn go_to_voxel(
-    input: Res<ButtonInput<KeyCode>>,
+    actions: Actions,
     cursor: Query<(&HexId, &MapDirection), With<cursor::Cursor>>,
     hexes: Query<(&HexId, &HexagonType)>,
     mut hex_select: ResMut<HexSelect>,
+    mut interiors: ResMut<Interiors>,
+    registry: Res<BlockRegistry>,
     mut next_screen: ResMut<NextState<Screen>>,
 ) {
-    if input.just_pressed(KeyCode::Enter) {
-        let cursor = cursor.single();
-        let mut hex_type = HexagonType::Empty;
-        for (id, hex) in &hexes {
-            if id == cursor.0 {
-                hex_type = *hex;
-                break;
-            }
-        }
-        let a = *cursor.1;
+    if actions.just_pressed(Action::Interact) {
+        let (id, direction) = cursor.single();
+        let hex_type = cell_type(&hexes, *id);
 
+        // Make sure the interior exists before the voxel world tries to spawn it.
+        interiors.get_or_generate(*id, hex_type, &registry);
         *hex_select = HexSelect {
-            hex_id: Vec2::new(cursor.0.x(), cursor.0.y()),
-            direction: *cursor.1,
+            hex_id: *id,
+            direction: *direction,
         };
-        // ! Fix type later
-        //hex_type: hex_type as u8,
         next_screen.set(Screen::VoxelWorld);
     }
 }
+
+/// Finds the type of the cell at `id`, treating cells that were never spawned as empty.
+pub fn cell_type(hexes: &Query<(&HexId, &HexagonType)>, id: HexId) -> HexagonType {
+    hexes
+        .iter()
+        .find(|(hex, _)| **hex == id)
+        .map(|(_, hex_type)| *hex_type)
+        .unwrap_or(HexagonType::Empty)
+}
+
 pub use constants::*;
 mod constants {
     pub const SQR_3: f32 = 1.732050807568877;
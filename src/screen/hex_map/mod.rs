@@ -1,13 +1,16 @@
 //! The screen state for the main hex map game loop.
 mod bundle;
-mod cells;
+pub mod cells;
 mod cursor;
+pub mod exploration;
+pub mod extraction;
 mod hex_util;
-use bevy::{input::common_conditions::input_just_pressed, prelude::*};
-use cells::CellIcons;
+mod overlay;
+pub mod tiles;
+use bevy::prelude::*;
 use hex_util::{go_to_voxel, spawn_test_grid};
 
-use super::Screen;
+use super::{pause::Pause, voxel_world::registry::BlockRegistry, Screen};
 use crate::game::{
     assets::SoundtrackKey, audio::soundtrack::PlaySoundtrack, spawn::player::SpawnPlayer,
 };
@@ -17,19 +20,33 @@ pub(super) fn plugin(app: &mut App) {
     app.add_systems(OnExit(Screen::HexMap), exit_playing);
     app.add_systems(PreUpdate, cells::update_transforms);
 
+    app.add_plugins((
+        cursor::CursorPlugin,
+        exploration::plugin,
+        extraction::plugin,
+        overlay::plugin,
+        tiles::plugin,
+    ));
     app.add_systems(
         Update,
-        return_to_title_screen
-            .run_if(in_state(Screen::HexMap).and_then(input_just_pressed(KeyCode::Escape))),
+        cells::setup_cell_icons.run_if(resource_exists_and_changed::<BlockRegistry>),
+    );
+    app.add_systems(
+        Update,
+        cells::update_cell_types.run_if(
+            in_state(Screen::HexMap)
+                .and_then(resource_exists::<BlockRegistry>)
+                .and_then(resource_exists::<tiles::HexTiles>),
+        ),
     );
-
-    app.add_plugins(cursor::CursorPlugin)
-        .init_resource::<CellIcons>();
 
     #[cfg(debug_assertions)]
     // todo Remove from game
     app.add_systems(OnEnter(Screen::HexMap), spawn_test_grid)
-        .add_systems(Update, go_to_voxel.run_if(in_state(Screen::HexMap)));
+        .add_systems(
+            Update,
+            go_to_voxel.run_if(in_state(Screen::HexMap).and_then(in_state(Pause::Running))),
+        );
 }
 
 fn enter_playing(mut commands: Commands) {
@@ -41,7 +58,3 @@ fn exit_playing(mut commands: Commands) {
     // We could use [`StateScoped`] on the sound playing entites instead.
     commands.trigger(PlaySoundtrack::Disable);
 }
-
-fn return_to_title_screen(mut next_screen: ResMut<NextState<Screen>>) {
-    next_screen.set(Screen::Title);
-}
@@ -0,0 +1,178 @@
+//! Overlays drawn over the cells of the hex map, cycled through by the player.
+//! Every cell gets a tile on the overlay layer, tinted by whichever overlay is shown.
+
+use bevy::{prelude::*, ui::Val::*, utils::HashMap};
+
+use super::{
+    bundle::HexCellBundle,
+    cells::{HexId, HexagonType},
+    exploration::Exploration,
+    tiles::{HexTiles, MapLayer, Tile},
+};
+use crate::{
+    game::input::{action_just_pressed, Action},
+    screen::{
+        voxel_world::{interiors::Interiors, VoxelData},
+        Screen,
+    },
+    ui::palette::LABEL_TEXT,
+    AppSet,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<MapOverlay>();
+    app.add_systems(OnEnter(Screen::HexMap), spawn_overlay_label);
+    app.add_systems(
+        Update,
+        (
+            cycle_overlay.run_if(action_just_pressed(Action::CycleOverlay)),
+            spawn_overlay_tiles.run_if(resource_exists::<HexTiles>),
+            (update_overlay_tiles, update_overlay_label),
+        )
+            .chain()
+            .in_set(AppSet::Update)
+            .run_if(in_state(Screen::HexMap)),
+    );
+}
+
+/// What is drawn over the cells.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapOverlay {
+    #[default]
+    None,
+    /// How much of its resource is left in each cell, from red to green.
+    Resources,
+    /// The cells the player has been inside of.
+    Visited,
+}
+
+impl MapOverlay {
+    fn next(self) -> Self {
+        match self {
+            MapOverlay::None => MapOverlay::Resources,
+            MapOverlay::Resources => MapOverlay::Visited,
+            MapOverlay::Visited => MapOverlay::None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            MapOverlay::None => "None",
+            MapOverlay::Resources => "Resources",
+            MapOverlay::Visited => "Visited",
+        }
+    }
+}
+
+/// Share of a cell made of its resource that shows as fully green.
+const FULL_RESOURCES: f32 = 0.25;
+
+const VISITED_TINT: Color = Color::srgba(0.3, 0.6, 1.0, 0.4);
+
+/// The overlay tile of a cell.
+#[derive(Component)]
+struct OverlayTile;
+
+/// Text telling which overlay is shown.
+#[derive(Component)]
+struct OverlayLabel;
+
+fn cycle_overlay(mut overlay: ResMut<MapOverlay>) {
+    *overlay = overlay.next();
+}
+
+/// Give every new cell a tile on the overlay layer.
+fn spawn_overlay_tiles(
+    mut commands: Commands,
+    cells: Query<&HexId, Added<HexagonType>>,
+    tiles: Res<HexTiles>,
+) {
+    for id in &cells {
+        commands.spawn((
+            Name::new("Overlay Tile"),
+            OverlayTile,
+            StateScoped(Screen::HexMap),
+            HexCellBundle {
+                id: *id,
+                transform: Transform::from_translation(Vec3::Z * MapLayer::Overlay.z()),
+                texture: tiles.image(),
+                atlas: tiles.atlas(Tile::Fill),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn update_overlay_tiles(
+    overlay: Res<MapOverlay>,
+    exploration: Res<Exploration>,
+    interiors: Res<Interiors>,
+    cells: Query<(&HexId, &HexagonType)>,
+    mut tiles: Query<(&HexId, &mut Sprite), With<OverlayTile>>,
+    // Resources are only counted again when an interior changes.
+    mut counted: Local<HashMap<HexId, (VoxelData, f32)>>,
+) {
+    let types: HashMap<HexId, HexagonType> = cells.iter().map(|(id, hex)| (*id, *hex)).collect();
+    for (id, mut sprite) in &mut tiles {
+        let color = match *overlay {
+            MapOverlay::None => Color::NONE,
+            MapOverlay::Visited if exploration.is_visited(*id) => VISITED_TINT,
+            MapOverlay::Visited => Color::NONE,
+            MapOverlay::Resources => match types.get(id) {
+                Some(hex_type) if hex_type.resource().is_some() => {
+                    // Cells that were never generated are untouched.
+                    let share = interiors
+                        .get(*id)
+                        .map_or(FULL_RESOURCES, |interior| match counted.get(id) {
+                            Some((seen, share)) if seen == interior => *share,
+                            _ => {
+                                let share = hex_type.resource_share(interior);
+                                counted.insert(*id, (interior.clone(), share));
+                                share
+                            }
+                        });
+                    let heat = (share / FULL_RESOURCES).min(1.);
+                    Color::srgba(1. - heat, heat, 0., 0.5)
+                }
+                _ => Color::NONE,
+            },
+        };
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
+
+fn spawn_overlay_label(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Overlay Label"),
+        OverlayLabel,
+        StateScoped(Screen::HexMap),
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.0,
+                color: LABEL_TEXT,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Px(10.0),
+            right: Px(10.0),
+            ..default()
+        }),
+    ));
+}
+
+fn update_overlay_label(
+    overlay: Res<MapOverlay>,
+    mut labels: Query<&mut Text, With<OverlayLabel>>,
+) {
+    let label = format!("Overlay: {}", overlay.name());
+    for mut text in &mut labels {
+        if text.sections[0].value != label {
+            text.sections[0].value.clone_from(&label);
+        }
+    }
+}
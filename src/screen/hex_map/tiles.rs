@@ -0,0 +1,178 @@
+//! Every image drawn on the hex map, packed into a single atlas.
+//! All map sprites share the atlas texture, so Bevy draws each layer in one batch.
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+    sprite::TextureAtlasBuilder,
+    utils::{HashMap, HashSet},
+};
+
+use super::{
+    cells::{CellIcons, HexagonType},
+    hex_util::SQR_3_DIV_TWO,
+};
+use crate::game::assets::{HandleMap, ImageKey};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        build_hex_tiles
+            .run_if(resource_exists::<CellIcons>.and_then(not(resource_exists::<HexTiles>))),
+    );
+}
+
+/// Layers of the hex map, from back to front.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapLayer {
+    /// The cells themselves.
+    Terrain,
+    /// Things built on cells, like extractors.
+    Structures,
+    /// The overlay picked by the player, see [`super::overlay`].
+    Overlay,
+    /// Fog over the cells the player can't see, see [`super::exploration`].
+    Fog,
+    /// The cursor.
+    Selection,
+}
+
+impl MapLayer {
+    pub fn z(self) -> f32 {
+        match self {
+            MapLayer::Terrain => -10.,
+            MapLayer::Structures => -9.75,
+            MapLayer::Overlay => -9.5,
+            MapLayer::Fog => -9.25,
+            MapLayer::Selection => -9.,
+        }
+    }
+}
+
+/// An image in the hex map atlas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tile {
+    /// The icon of a kind of cell.
+    Cell(HexagonType),
+    /// The outline of a cell.
+    Outline,
+    /// A white hexagon, tinted to color a cell.
+    Fill,
+}
+
+/// The hex map atlas and where each tile is in it.
+#[derive(Resource)]
+pub struct HexTiles {
+    image: Handle<Image>,
+    layout: Handle<TextureAtlasLayout>,
+    indices: HashMap<Tile, usize>,
+}
+
+impl HexTiles {
+    pub fn image(&self) -> Handle<Image> {
+        self.image.clone()
+    }
+
+    /// Part of the atlas showing `tile`. Tiles whose image failed to load show the first tile.
+    pub fn atlas(&self, tile: Tile) -> TextureAtlas {
+        TextureAtlas {
+            layout: self.layout.clone(),
+            index: self.indices.get(&tile).copied().unwrap_or_default(),
+        }
+    }
+}
+
+/// Size of the generated [`Tile::Fill`] image.
+const FILL_SIZE: u32 = 64;
+
+/// A white flat-topped hexagon filling the width of the image, like the cell icons.
+fn hexagon_fill() -> Image {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: FILL_SIZE,
+            height: FILL_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    let radius = FILL_SIZE as f32 / 2.;
+    for y in 0..FILL_SIZE {
+        for x in 0..FILL_SIZE {
+            let x_offset = (x as f32 + 0.5 - radius).abs();
+            let y_offset = (y as f32 + 0.5 - radius).abs();
+            let inside = y_offset <= SQR_3_DIV_TWO * radius
+                && x_offset * SQR_3_DIV_TWO + y_offset / 2. <= SQR_3_DIV_TWO * radius;
+            if inside {
+                let index = ((y * FILL_SIZE + x) * 4) as usize;
+                image.data[index..index + 4].copy_from_slice(&[255; 4]);
+            }
+        }
+    }
+    image
+}
+
+/// Pack the cell icons and the outline into the atlas once they are all loaded.
+/// Runs again whenever the icons are reloaded along with the block registry.
+fn build_hex_tiles(
+    mut commands: Commands,
+    icons: Res<CellIcons>,
+    image_handles: Res<HandleMap<ImageKey>>,
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    let (done, total) = icons.progress(&asset_server);
+    let (images_done, images_total) = image_handles.progress(&asset_server);
+    if done < total || images_done < images_total {
+        return;
+    }
+
+    let tiles: Vec<(Tile, Handle<Image>)> = icons
+        .iter()
+        .map(|(hex_type, icon)| (Tile::Cell(*hex_type), icon.clone_weak()))
+        .chain([
+            (
+                Tile::Outline,
+                image_handles[&ImageKey::HexOutline].clone_weak(),
+            ),
+            (Tile::Fill, images.add(hexagon_fill())),
+        ])
+        .collect();
+
+    let (layout, atlas) = {
+        let mut builder = TextureAtlasBuilder::default();
+        // Some kinds of cells share their icon, which only needs to be packed once.
+        let mut packed = HashSet::new();
+        for (_, image) in &tiles {
+            if !packed.insert(image.id()) {
+                continue;
+            }
+            if let Some(texture) = images.get(image) {
+                builder.add_texture(Some(image.id()), texture);
+            }
+        }
+        match builder.build() {
+            Ok(atlas) => atlas,
+            Err(error) => {
+                error!("Could not build the hex map atlas: {error}");
+                return;
+            }
+        }
+    };
+
+    let indices = tiles
+        .iter()
+        .filter_map(|(tile, image)| Some((*tile, layout.get_texture_index(image.id())?)))
+        .collect();
+    commands.insert_resource(HexTiles {
+        image: images.add(atlas),
+        layout: layouts.add(layout),
+        indices,
+    });
+}
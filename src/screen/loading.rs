@@ -1,11 +1,18 @@
 //! A loading screen during which game assets are loaded.
 //! This reduces stuttering, especially for audio on WASM.
 
-use bevy::prelude::*;
+use bevy::{asset::LoadState, ecs::system::SystemParam, prelude::*, ui::Val::*};
 
-use super::Screen;
+use super::{
+    hex_map::{cells::HexagonType, tiles::HexTiles},
+    voxel_world::{
+        registry::{BlockId, BlockSound},
+        voxel_util::Blocks,
+    },
+    Screen,
+};
 use crate::{
-    game::assets::{HandleMap, ImageKey, SfxKey, SoundtrackKey},
+    game::assets::{DataKey, HandleMap, ImageKey, SfxKey, SoundtrackKey},
     ui::prelude::*,
 };
 
@@ -13,28 +20,165 @@ pub(super) fn plugin(app: &mut App) {
     app.add_systems(OnEnter(Screen::Loading), enter_loading);
     app.add_systems(
         Update,
-        continue_to_title.run_if(in_state(Screen::Loading).and_then(all_assets_loaded)),
+        (update_progress_bar, continue_to_title.run_if(loading_done))
+            .chain()
+            .run_if(in_state(Screen::Loading)),
     );
 }
 
+/// The filled part of the progress bar.
+#[derive(Component)]
+struct ProgressFill;
+
+/// The "loaded / total" label under the progress bar.
+#[derive(Component)]
+struct ProgressText;
+
 fn enter_loading(mut commands: Commands) {
     commands
         .ui_root()
         .insert(StateScoped(Screen::Loading))
         .with_children(|children| {
             children.label("Loading...");
+            children
+                .spawn((
+                    Name::new("Progress Bar"),
+                    NodeBundle {
+                        style: Style {
+                            width: Px(500.0),
+                            height: Px(30.0),
+                            ..default()
+                        },
+                        background_color: BackgroundColor(Color::BLACK),
+                        ..default()
+                    },
+                ))
+                .with_children(|children| {
+                    children.spawn((
+                        Name::new("Progress Fill"),
+                        ProgressFill,
+                        NodeBundle {
+                            style: Style {
+                                width: Percent(0.0),
+                                height: Percent(100.0),
+                                ..default()
+                            },
+                            background_color: BackgroundColor(ui_palette::NODE_BACKGROUND),
+                            ..default()
+                        },
+                    ));
+                });
+            children.spawn((
+                Name::new("Progress Text"),
+                ProgressText,
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 24.0,
+                        color: ui_palette::LABEL_TEXT,
+                        ..default()
+                    },
+                ),
+            ));
         });
 }
 
-fn all_assets_loaded(
-    asset_server: Res<AssetServer>,
-    image_handles: Res<HandleMap<ImageKey>>,
-    sfx_handles: Res<HandleMap<SfxKey>>,
-    soundtrack_handles: Res<HandleMap<SoundtrackKey>>,
-) -> bool {
-    image_handles.all_loaded(&asset_server)
-        && sfx_handles.all_loaded(&asset_server)
-        && soundtrack_handles.all_loaded(&asset_server)
+/// Everything that has to be loaded before leaving the loading screen.
+/// Block textures, sounds and hex icons depend on the block registry, so their handle maps
+/// only exist once the registry itself has loaded. Both atlases are built from them afterwards.
+#[derive(SystemParam)]
+struct LoadingProgress<'w> {
+    asset_server: Res<'w, AssetServer>,
+    image_handles: Res<'w, HandleMap<ImageKey>>,
+    sfx_handles: Res<'w, HandleMap<SfxKey>>,
+    soundtrack_handles: Res<'w, HandleMap<SoundtrackKey>>,
+    data_handles: Res<'w, HandleMap<DataKey>>,
+    block_textures: Option<Res<'w, HandleMap<BlockId>>>,
+    cell_icons: Option<Res<'w, HandleMap<HexagonType>>>,
+    block_sounds: Option<Res<'w, HandleMap<(BlockId, BlockSound)>>>,
+    blocks: Option<Res<'w, Blocks>>,
+    hex_tiles: Option<Res<'w, HexTiles>>,
+}
+
+impl LoadingProgress<'_> {
+    /// How many assets are done loading, out of the total known so far.
+    fn progress(&self) -> (usize, usize) {
+        let asset_server = &self.asset_server;
+        [
+            Some(self.image_handles.progress(asset_server)),
+            Some(self.sfx_handles.progress(asset_server)),
+            Some(self.soundtrack_handles.progress(asset_server)),
+            Some(self.data_handles.progress(asset_server)),
+            self.block_textures
+                .as_ref()
+                .map(|handles| handles.progress(asset_server)),
+            self.cell_icons
+                .as_ref()
+                .map(|handles| handles.progress(asset_server)),
+            self.block_sounds
+                .as_ref()
+                .map(|handles| handles.progress(asset_server)),
+        ]
+        .into_iter()
+        .flatten()
+        .fold((0, 0), |(done, total), (d, t)| (done + d, total + t))
+    }
+
+    /// Why the block registry failed to load, if it did. Nothing can be played without it, so
+    /// loading stops there rather than waiting for a registry that will never come.
+    fn registry_error(&self) -> Option<String> {
+        match self
+            .asset_server
+            .get_load_state(&self.data_handles[&DataKey::Blocks])
+        {
+            Some(LoadState::Failed(error)) => Some(error.to_string()),
+            _ => None,
+        }
+    }
+
+    fn done(&self) -> bool {
+        let (done, total) = self.progress();
+        done == total
+            && self.block_textures.is_some()
+            && self.cell_icons.is_some()
+            && self.block_sounds.is_some()
+            && self.blocks.is_some()
+            && self.hex_tiles.is_some()
+    }
+}
+
+fn update_progress_bar(
+    progress: LoadingProgress,
+    mut fill: Query<&mut Style, With<ProgressFill>>,
+    mut text: Query<&mut Text, With<ProgressText>>,
+    mut reported: Local<bool>,
+) {
+    if let Some(error) = progress.registry_error() {
+        if !*reported {
+            error!("Could not load the block registry: {error}");
+            *reported = true;
+        }
+        for mut text in &mut text {
+            text.sections[0].value = format!("Could not load the block registry:\n{error}");
+        }
+        return;
+    }
+    let (done, total) = progress.progress();
+    let fraction = if total == 0 {
+        0.0
+    } else {
+        done as f32 / total as f32
+    };
+    for mut style in &mut fill {
+        style.width = Percent(fraction * 100.0);
+    }
+    for mut text in &mut text {
+        text.sections[0].value = format!("{done} / {total}");
+    }
+}
+
+fn loading_done(progress: LoadingProgress) -> bool {
+    progress.done()
 }
 
 fn continue_to_title(mut next_screen: ResMut<NextState<Screen>>) {
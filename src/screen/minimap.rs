@@ -0,0 +1,352 @@
+//! A minimap in the corner of the hex map and of the voxel world.
+//! It shows the explored cells around the player, coloured by their kind, and in the voxel world
+//! also a top-down view of the interior the player is in. Clicking a cell on the minimap of the
+//! hex map moves the camera over it.
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+    ui::Val::*,
+    utils::HashMap,
+    window::PrimaryWindow,
+};
+
+use super::{
+    hex_map::{
+        cells::{HexId, HexagonType},
+        exploration::Exploration,
+    },
+    voxel_world::{
+        interiors::Interiors, registry::BlockRegistry, voxel_util::VoxelPlayer, VoxelData,
+        VOXEL_DIVISION_FACTOR,
+    },
+    HexSelect, Screen,
+};
+use crate::{
+    game::{save::LoadGame, spawn::player::Player},
+    AppSet,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<KnownCells>();
+    app.observe(forget_cells);
+    app.add_systems(OnEnter(Screen::HexMap), spawn_minimap(Screen::HexMap));
+    app.add_systems(
+        OnEnter(Screen::VoxelWorld),
+        spawn_minimap(Screen::VoxelWorld),
+    );
+    app.add_systems(OnExit(Screen::HexMap), reset_camera);
+    app.add_systems(
+        Update,
+        (remember_cells, center_camera)
+            .in_set(AppSet::Update)
+            .run_if(in_state(Screen::HexMap)),
+    );
+    app.add_systems(
+        Update,
+        draw_interior_map
+            .in_set(AppSet::Update)
+            .run_if(in_state(Screen::VoxelWorld).and_then(resource_exists::<BlockRegistry>)),
+    );
+    app.add_systems(
+        Update,
+        draw_hex_map.in_set(AppSet::Update).run_if(
+            in_state(Screen::HexMap)
+                .or_else(in_state(Screen::VoxelWorld))
+                .and_then(resource_exists::<BlockRegistry>),
+        ),
+    );
+}
+
+/// Width and height of the hex minimap, in pixels.
+const HEX_MAP_SIZE: u32 = 120;
+/// Hex map distance covered by one pixel of the minimap.
+const WORLD_PER_PIXEL: f32 = 7.5;
+/// Size the interior minimap is shown at, in UI pixels. Each block is one pixel of the image.
+const INTERIOR_MAP_SIZE: f32 = 120.;
+
+const EMPTY_CELL: [f32; 3] = [0.35, 0.35, 0.35];
+const VOXEL_CELL: [f32; 3] = [0.6, 0.8, 1.0];
+const PLAYER: [f32; 3] = [1., 0.2, 0.2];
+const BACKGROUND: Color = Color::srgba(0., 0., 0., 0.6);
+
+/// The kind of every cell spawned on the hex map, so the minimap still knows them once the cells
+/// are despawned in the voxel world.
+#[derive(Resource, Default)]
+struct KnownCells(HashMap<HexId, HexagonType>);
+
+/// The image showing the cells around the player.
+#[derive(Component)]
+struct HexMinimap;
+
+/// The image showing the interior the player is in.
+#[derive(Component)]
+struct InteriorMinimap;
+
+fn remember_cells(
+    cells: Query<(&HexId, &HexagonType), Changed<HexagonType>>,
+    mut known: ResMut<KnownCells>,
+) {
+    for (id, hex_type) in &cells {
+        known.0.insert(*id, *hex_type);
+    }
+}
+
+/// Cells of another game don't apply to the one being loaded.
+fn forget_cells(_trigger: Trigger<LoadGame>, mut known: ResMut<KnownCells>) {
+    known.0.clear();
+}
+
+/// A transparent image the minimap draws into.
+fn minimap_image(width: u32, height: u32) -> Image {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::nearest();
+    image
+}
+
+/// Writes `color` to the pixel at `x`, `y`, with `alpha` from 0 to 255.
+fn put_pixel(image: &mut Image, x: u32, y: u32, [red, green, blue]: [f32; 3], alpha: u8) {
+    let channel = |value: f32| (value.clamp(0., 1.) * 255.) as u8;
+    let index = ((y * image.width() + x) * 4) as usize;
+    image.data[index..index + 4].copy_from_slice(&[
+        channel(red),
+        channel(green),
+        channel(blue),
+        alpha,
+    ]);
+}
+
+fn spawn_minimap(screen: Screen) -> impl Fn(Commands, ResMut<Assets<Image>>) {
+    move |mut commands: Commands, mut images: ResMut<Assets<Image>>| {
+        let hex_map = images.add(minimap_image(HEX_MAP_SIZE, HEX_MAP_SIZE));
+        let size = VOXEL_DIVISION_FACTOR as u32;
+        let interior = images.add(minimap_image(size, size));
+        commands
+            .spawn((
+                Name::new("Minimap"),
+                StateScoped(screen.clone()),
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        top: Px(50.0),
+                        right: Px(10.0),
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Px(10.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    Name::new("Hex Minimap"),
+                    HexMinimap,
+                    Interaction::default(),
+                    ImageBundle {
+                        style: Style {
+                            width: Px(HEX_MAP_SIZE as f32),
+                            height: Px(HEX_MAP_SIZE as f32),
+                            ..default()
+                        },
+                        background_color: BACKGROUND.into(),
+                        image: UiImage::new(hex_map),
+                        ..default()
+                    },
+                ));
+                if screen == Screen::VoxelWorld {
+                    parent.spawn((
+                        Name::new("Interior Minimap"),
+                        InteriorMinimap,
+                        ImageBundle {
+                            style: Style {
+                                width: Px(INTERIOR_MAP_SIZE),
+                                height: Px(INTERIOR_MAP_SIZE),
+                                ..default()
+                            },
+                            background_color: BACKGROUND.into(),
+                            image: UiImage::new(interior),
+                            ..default()
+                        },
+                    ));
+                }
+            });
+    }
+}
+
+/// The cell the player is in on either screen.
+fn player_cell(
+    screen: &State<Screen>,
+    player: &Query<&Transform, With<Player>>,
+    hex_select: &HexSelect,
+) -> Option<HexId> {
+    match screen.get() {
+        Screen::VoxelWorld => Some(hex_select.hex_id),
+        _ => Some(HexId::from_xyz(player.get_single().ok()?.translation)),
+    }
+}
+
+/// Where on the hex map the pixel at `x`, `y` of a minimap centred on `center` is.
+fn pixel_position(center: HexId, x: f32, y: f32) -> Vec3 {
+    let half = HEX_MAP_SIZE as f32 / 2.;
+    center.xyz() + Vec3::new(x - half, half - y, 0.) * WORLD_PER_PIXEL
+}
+
+fn cell_color(hex_type: Option<&HexagonType>, registry: &BlockRegistry) -> [f32; 3] {
+    match hex_type {
+        Some(HexagonType::Block(id)) => registry.get(*id).map_or(EMPTY_CELL, |block| block.color),
+        Some(HexagonType::Voxel) => VOXEL_CELL,
+        Some(HexagonType::Empty) | None => EMPTY_CELL,
+    }
+}
+
+fn draw_hex_map(
+    screen: Res<State<Screen>>,
+    player: Query<&Transform, With<Player>>,
+    hex_select: Res<HexSelect>,
+    exploration: Res<Exploration>,
+    known: Res<KnownCells>,
+    registry: Res<BlockRegistry>,
+    minimaps: Query<&UiImage, With<HexMinimap>>,
+    new_minimaps: Query<(), Added<HexMinimap>>,
+    mut images: ResMut<Assets<Image>>,
+    mut drawn: Local<Option<HexId>>,
+) {
+    let Some(center) = player_cell(&screen, &player, &hex_select) else {
+        return;
+    };
+    if *drawn == Some(center)
+        && new_minimaps.is_empty()
+        && !exploration.is_changed()
+        && !known.is_changed()
+    {
+        return;
+    }
+    *drawn = Some(center);
+    for minimap in &minimaps {
+        let Some(image) = images.get_mut(&minimap.texture) else {
+            continue;
+        };
+        for y in 0..HEX_MAP_SIZE {
+            for x in 0..HEX_MAP_SIZE {
+                let id = HexId::from_xyz(pixel_position(center, x as f32 + 0.5, y as f32 + 0.5));
+                if id == center {
+                    put_pixel(image, x, y, PLAYER, 255);
+                } else if exploration.is_seen(id) {
+                    put_pixel(image, x, y, cell_color(known.0.get(&id), &registry), 255);
+                } else {
+                    put_pixel(image, x, y, [0.; 3], 0);
+                }
+            }
+        }
+    }
+}
+
+/// Draws the interior from above, showing the first block under the player's eyes in every
+/// column, darker the deeper it is.
+fn draw_interior_map(
+    camera: Query<&GlobalTransform, With<VoxelPlayer>>,
+    hex_select: Res<HexSelect>,
+    interiors: Res<Interiors>,
+    registry: Res<BlockRegistry>,
+    minimaps: Query<&UiImage, With<InteriorMinimap>>,
+    new_minimaps: Query<(), Added<InteriorMinimap>>,
+    mut images: ResMut<Assets<Image>>,
+    mut drawn: Local<Option<(IVec3, VoxelData)>>,
+) {
+    let (Ok(camera), Some(interior)) = (camera.get_single(), interiors.get(hex_select.hex_id))
+    else {
+        return;
+    };
+    let eye = camera.translation().round().as_ivec3();
+    let unchanged = drawn
+        .as_ref()
+        .is_some_and(|(seen, old)| *seen == eye && old.shares_blocks(interior));
+    if unchanged && new_minimaps.is_empty() {
+        return;
+    }
+    *drawn = Some((eye, interior.clone()));
+    let size = VOXEL_DIVISION_FACTOR as i32;
+    for minimap in &minimaps {
+        let Some(image) = images.get_mut(&minimap.texture) else {
+            continue;
+        };
+        for z in 0..size {
+            for x in 0..size {
+                let (pixel_x, pixel_y) = (x as u32, z as u32);
+                if x == eye.x && z == eye.z {
+                    put_pixel(image, pixel_x, pixel_y, PLAYER, 255);
+                    continue;
+                }
+                let top = (0..=eye.y.min(size - 1)).rev().find_map(|y| {
+                    let id = interior.get(IVec3::new(x, y, z))?.appearance()?;
+                    Some((eye.y - y, registry.get(id)?.color))
+                });
+                match top {
+                    Some((depth, color)) => {
+                        let shade = (1. - depth as f32 * 0.06).max(0.3);
+                        put_pixel(image, pixel_x, pixel_y, color.map(|c| c * shade), 255);
+                    }
+                    None => put_pixel(image, pixel_x, pixel_y, [0.; 3], 0),
+                }
+            }
+        }
+    }
+}
+
+/// Move the camera over the cell clicked on the minimap.
+fn center_camera(
+    minimaps: Query<
+        (&Interaction, &Node, &GlobalTransform),
+        (Changed<Interaction>, With<HexMinimap>),
+    >,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    player: Query<&Transform, With<Player>>,
+    mut cameras: Query<&mut Transform, (With<Camera2d>, Without<Player>)>,
+) {
+    let Some(cursor) = windows
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position())
+    else {
+        return;
+    };
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    for (interaction, node, transform) in &minimaps {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let rect = node.logical_rect(transform);
+        let pixel = (cursor - rect.min) / rect.size() * HEX_MAP_SIZE as f32;
+        let center = HexId::from_xyz(player.translation);
+        let target = HexId::from_xyz(pixel_position(center, pixel.x, pixel.y));
+        for mut camera in &mut cameras {
+            let position = target.xyz();
+            camera.translation.x = position.x;
+            camera.translation.y = position.y;
+        }
+    }
+}
+
+/// Other screens expect the camera where it started.
+fn reset_camera(mut cameras: Query<&mut Transform, With<Camera2d>>) {
+    for mut camera in &mut cameras {
+        camera.translation.x = 0.;
+        camera.translation.y = 0.;
+    }
+}
@@ -1,13 +1,18 @@
 //! The game's main screen states and transitions between them.
 
 mod credits;
-mod hex_map;
+pub mod hex_map;
 mod loading;
+mod minimap;
+pub mod pause;
+pub mod settings;
 mod splash;
 mod title;
 pub mod voxel_world;
 
 use bevy::prelude::*;
+use hex_map::cells::HexId;
+use serde::{Deserialize, Serialize};
 
 pub(super) fn plugin(app: &mut App) {
     app.init_state::<Screen>();
@@ -20,10 +25,13 @@ pub(super) fn plugin(app: &mut App) {
         credits::plugin,
         hex_map::plugin,
         voxel_world::plugin,
+        pause::plugin,
+        settings::plugin,
+        minimap::plugin,
     ));
 
     app.insert_resource(HexSelect {
-        hex_id: Vec2::new(0.0, 0.0),
+        hex_id: HexId::new(0, 0),
         direction: MapDirection::Up,
     });
 }
@@ -43,7 +51,18 @@ pub enum Screen {
 
 /// This represents the edges of the hexagon mapping to the voxel world.
 /// The Direction with reference to the hexagon is in clockwise order for the enum, starting from the top edge.
-#[derive(Clone, Copy, PartialEq, strum_macros::EnumIter, Debug, Component, Eq, Hash)]
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    strum_macros::EnumIter,
+    Debug,
+    Component,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
 pub enum MapDirection {
     Up,
     North,
@@ -56,6 +75,6 @@ pub enum MapDirection {
 /// The current selected hexagon
 #[derive(Resource, Debug)]
 pub struct HexSelect {
-    pub hex_id: Vec2,
+    pub hex_id: HexId,
     pub direction: MapDirection,
 }
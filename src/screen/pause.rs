@@ -0,0 +1,132 @@
+//! Pausing the hex map and the voxel world.
+//! Gameplay systems all live in an [`AppSet`], so pausing them is a single run condition.
+
+use bevy::{prelude::*, ui::FocusPolicy};
+use bevy_rapier3d::prelude::RapierConfiguration;
+
+use super::{settings::SettingsMenu, Screen};
+use crate::{
+    game::{
+        input::{action_just_pressed, Action},
+        save::SaveGame,
+    },
+    ui::prelude::*,
+    AppSet,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_sub_state::<Pause>();
+    app.enable_state_scoped_entities::<Pause>();
+    app.configure_sets(
+        Update,
+        (AppSet::TickTimers, AppSet::RecordInput, AppSet::Update)
+            .run_if(not(in_state(Pause::Paused))),
+    );
+
+    app.add_systems(OnEnter(Pause::Paused), (enter_pause, freeze_physics));
+    app.add_systems(OnExit(Pause::Paused), unfreeze_physics);
+
+    app.register_type::<PauseAction>();
+    // The settings menu handles Back itself while it is open.
+    app.add_systems(
+        Update,
+        (
+            toggle_pause.run_if(state_exists::<Pause>.and_then(action_just_pressed(Action::Back))),
+            handle_pause_action.run_if(in_state(Pause::Paused)),
+        )
+            .run_if(in_state(SettingsMenu::Closed)),
+    );
+}
+
+/// Whether gameplay is paused. Only exists on screens that can be paused.
+#[derive(SubStates, Debug, Hash, PartialEq, Eq, Clone, Default)]
+#[source(Screen = Screen::HexMap | Screen::VoxelWorld)]
+pub enum Pause {
+    #[default]
+    Running,
+    Paused,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+enum PauseAction {
+    Resume,
+    Settings,
+    Save,
+    ReturnToMap,
+    QuitToTitle,
+}
+
+fn enter_pause(mut commands: Commands, screen: Res<State<Screen>>) {
+    commands
+        .ui_root()
+        .insert((
+            Name::new("Pause Root"),
+            StateScoped(Pause::Paused),
+            BackgroundColor(Color::srgba(0., 0., 0., 0.7)),
+            FocusPolicy::Block,
+            ZIndex::Global(5),
+        ))
+        .with_children(|children| {
+            children.header("Paused");
+            children.small_button("Resume").insert(PauseAction::Resume);
+            children
+                .small_button("Settings")
+                .insert(PauseAction::Settings);
+            children.small_button("Save").insert(PauseAction::Save);
+            if *screen.get() == Screen::VoxelWorld {
+                children
+                    .small_button("Return to map")
+                    .insert(PauseAction::ReturnToMap);
+            }
+            children
+                .small_button("Quit to title")
+                .insert(PauseAction::QuitToTitle);
+        });
+}
+
+fn freeze_physics(mut config: ResMut<RapierConfiguration>) {
+    config.physics_pipeline_active = false;
+}
+
+fn unfreeze_physics(mut config: ResMut<RapierConfiguration>) {
+    config.physics_pipeline_active = true;
+}
+
+fn toggle_pause(pause: Res<State<Pause>>, mut next_pause: ResMut<NextState<Pause>>) {
+    next_pause.set(match pause.get() {
+        Pause::Running => Pause::Paused,
+        Pause::Paused => Pause::Running,
+    });
+}
+
+fn handle_pause_action(
+    mut commands: Commands,
+    mut next_screen: ResMut<NextState<Screen>>,
+    mut next_pause: ResMut<NextState<Pause>>,
+    mut next_menu: ResMut<NextState<SettingsMenu>>,
+    mut button_query: InteractionQuery<(&PauseAction, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    for (interaction, (action, children)) in &mut button_query {
+        if matches!(interaction, Interaction::Pressed) {
+            match action {
+                PauseAction::Resume => next_pause.set(Pause::Running),
+                PauseAction::Settings => next_menu.set(SettingsMenu::Open),
+                PauseAction::Save => {
+                    commands.trigger(SaveGame);
+                    let mut texts = texts.iter_many_mut(children);
+                    while let Some(mut text) = texts.fetch_next() {
+                        text.sections[0].value = "Saved".to_string();
+                    }
+                }
+                PauseAction::ReturnToMap => {
+                    // The hex map is also pausable, so the pause would carry over without this.
+                    next_pause.set(Pause::Running);
+                    next_screen.set(Screen::HexMap);
+                }
+                PauseAction::QuitToTitle => next_screen.set(Screen::Title),
+            }
+        }
+    }
+}
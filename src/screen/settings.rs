@@ -0,0 +1,323 @@
+//! The settings menu. It is an overlay with its own state rather than a [`Screen`](super::Screen)
+//! so it can be opened on top of other screens without leaving them.
+
+use bevy::{ecs::system::EntityCommands, prelude::*, ui::FocusPolicy};
+use strum::IntoEnumIterator;
+
+use crate::{
+    game::{
+        input::{Action, Actions, Inputs},
+        settings::Settings,
+    },
+    ui::prelude::*,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_state::<SettingsMenu>();
+    app.enable_state_scoped_entities::<SettingsMenu>();
+    app.init_resource::<Rebinding>();
+
+    app.add_systems(OnEnter(SettingsMenu::Open), enter_settings);
+    app.add_systems(OnExit(SettingsMenu::Open), save_settings);
+    app.add_systems(OnEnter(SettingsMenu::Controls), enter_controls);
+    app.add_systems(
+        OnExit(SettingsMenu::Controls),
+        (save_settings, stop_rebinding),
+    );
+
+    app.register_type::<(SettingsAction, BindingButton)>();
+    app.add_systems(
+        Update,
+        (handle_settings_action, apply_sliders, close_settings)
+            .run_if(in_state(SettingsMenu::Open)),
+    );
+    app.add_systems(
+        Update,
+        (
+            capture_binding,
+            handle_controls_action,
+            leave_controls,
+            update_binding_buttons,
+        )
+            .chain()
+            .run_if(in_state(SettingsMenu::Controls)),
+    );
+}
+
+/// Which page of the settings menu is shown, if any.
+#[derive(States, Debug, Hash, PartialEq, Eq, Clone, Default)]
+pub enum SettingsMenu {
+    #[default]
+    Closed,
+    Open,
+    Controls,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+enum SettingsAction {
+    InvertY,
+    Controls,
+    Back,
+}
+
+/// A button rebinding an action when pressed.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+enum BindingButton {
+    Action(Action),
+    Reset,
+    Back,
+}
+
+/// The action waiting for the player to press its new binding.
+#[derive(Resource, Default)]
+struct Rebinding(Option<Action>);
+
+/// The setting changed by a slider.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum SettingsSlider {
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    MouseSensitivity,
+    Fov,
+}
+
+impl SettingsSlider {
+    fn value_mut(self, settings: &mut Settings) -> &mut f32 {
+        match self {
+            SettingsSlider::MasterVolume => &mut settings.master_volume,
+            SettingsSlider::MusicVolume => &mut settings.music_volume,
+            SettingsSlider::SfxVolume => &mut settings.sfx_volume,
+            SettingsSlider::MouseSensitivity => &mut settings.mouse_sensitivity,
+            SettingsSlider::Fov => &mut settings.fov,
+        }
+    }
+}
+
+fn invert_y_text(invert_y: bool) -> &'static str {
+    if invert_y {
+        "Invert Y: On"
+    } else {
+        "Invert Y: Off"
+    }
+}
+
+/// Spawn the root of a settings page, covering whatever screen the menu was opened from.
+fn settings_root(commands: &mut Commands, page: SettingsMenu) -> EntityCommands {
+    let mut root = commands.ui_root();
+    root.insert((
+        Name::new("Settings Root"),
+        StateScoped(page),
+        BackgroundColor(Color::srgb(0.1, 0.1, 0.1)),
+        FocusPolicy::Block,
+        ZIndex::Global(10),
+    ));
+    root
+}
+
+fn enter_settings(mut commands: Commands, settings: Res<Settings>) {
+    settings_root(&mut commands, SettingsMenu::Open).with_children(|children| {
+        children.header("Settings");
+        let sliders = [
+            (
+                SettingsSlider::MasterVolume,
+                Slider::new("Master volume", settings.master_volume, 0., 1., 0.05),
+            ),
+            (
+                SettingsSlider::MusicVolume,
+                Slider::new("Music volume", settings.music_volume, 0., 1., 0.05),
+            ),
+            (
+                SettingsSlider::SfxVolume,
+                Slider::new("Effects volume", settings.sfx_volume, 0., 1., 0.05),
+            ),
+            (
+                SettingsSlider::MouseSensitivity,
+                Slider::new(
+                    "Mouse sensitivity",
+                    settings.mouse_sensitivity,
+                    0.1,
+                    3.,
+                    0.05,
+                ),
+            ),
+            (
+                SettingsSlider::Fov,
+                Slider::new("Field of view", settings.fov, 30., 110., 1.),
+            ),
+        ];
+        for (field, slider) in sliders {
+            children.slider(slider).insert(field);
+        }
+        children
+            .button(invert_y_text(settings.invert_y))
+            .insert(SettingsAction::InvertY);
+        children.button("Controls").insert(SettingsAction::Controls);
+        children.button("Back").insert(SettingsAction::Back);
+    });
+}
+
+fn enter_controls(mut commands: Commands) {
+    settings_root(&mut commands, SettingsMenu::Controls).with_children(|children| {
+        children.header("Controls");
+        // Two columns, so every action fits on the screen along with the buttons below.
+        children
+            .spawn((
+                Name::new("Bindings"),
+                NodeBundle {
+                    style: Style {
+                        display: Display::Grid,
+                        grid_template_columns: RepeatedGridTrack::auto(2),
+                        column_gap: Val::Px(10.0),
+                        row_gap: Val::Px(10.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+            ))
+            .with_children(|children| {
+                for action in Action::iter() {
+                    // The text is filled in by `update_binding_buttons`.
+                    children
+                        .small_button("")
+                        .insert(BindingButton::Action(action));
+                }
+            });
+        children
+            .small_button("Reset to defaults")
+            .insert(BindingButton::Reset);
+        children.small_button("Back").insert(BindingButton::Back);
+    });
+}
+
+fn save_settings(settings: Res<Settings>) {
+    settings.save();
+}
+
+fn handle_settings_action(
+    mut next_menu: ResMut<NextState<SettingsMenu>>,
+    mut settings: ResMut<Settings>,
+    mut button_query: InteractionQuery<(&SettingsAction, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    for (interaction, (action, children)) in &mut button_query {
+        if matches!(interaction, Interaction::Pressed) {
+            match action {
+                SettingsAction::InvertY => {
+                    settings.invert_y = !settings.invert_y;
+                    let mut texts = texts.iter_many_mut(children);
+                    while let Some(mut text) = texts.fetch_next() {
+                        text.sections[0].value = invert_y_text(settings.invert_y).to_string();
+                    }
+                }
+                SettingsAction::Controls => next_menu.set(SettingsMenu::Controls),
+                SettingsAction::Back => next_menu.set(SettingsMenu::Closed),
+            }
+        }
+    }
+}
+
+fn apply_sliders(
+    mut settings: ResMut<Settings>,
+    sliders: Query<(&Slider, &SettingsSlider), Changed<Slider>>,
+) {
+    for (slider, field) in &sliders {
+        if *field.value_mut(settings.bypass_change_detection()) != slider.value {
+            *field.value_mut(&mut settings) = slider.value;
+        }
+    }
+}
+
+fn close_settings(actions: Actions, mut next_menu: ResMut<NextState<SettingsMenu>>) {
+    if actions.just_pressed(Action::Back) {
+        next_menu.set(SettingsMenu::Closed);
+    }
+}
+
+fn handle_controls_action(
+    mut next_menu: ResMut<NextState<SettingsMenu>>,
+    mut settings: ResMut<Settings>,
+    mut rebinding: ResMut<Rebinding>,
+    button_query: InteractionQuery<&BindingButton>,
+) {
+    for (interaction, button) in &button_query {
+        if matches!(interaction, Interaction::Pressed) {
+            match button {
+                BindingButton::Action(action) => rebinding.0 = Some(*action),
+                BindingButton::Reset => settings.bindings = default(),
+                BindingButton::Back => next_menu.set(SettingsMenu::Open),
+            }
+        }
+    }
+}
+
+fn leave_controls(
+    actions: Actions,
+    rebinding: Res<Rebinding>,
+    mut next_menu: ResMut<NextState<SettingsMenu>>,
+) {
+    // Cancelling a rebind with Escape shouldn't also leave the page.
+    if rebinding.0.is_none() && !rebinding.is_changed() && actions.just_pressed(Action::Back) {
+        next_menu.set(SettingsMenu::Open);
+    }
+}
+
+/// Bind the next input the player presses to the action being rebound.
+/// Runs before the buttons are handled, so the click starting a rebind isn't captured.
+/// Escape always cancels, so the menu can't be locked out.
+fn capture_binding(
+    mut settings: ResMut<Settings>,
+    mut rebinding: ResMut<Rebinding>,
+    keys: Res<ButtonInput<KeyCode>>,
+    inputs: Inputs,
+) {
+    let Some(action) = rebinding.0 else {
+        return;
+    };
+    if keys.just_pressed(KeyCode::Escape) {
+        rebinding.0 = None;
+        return;
+    }
+    if let Some(binding) = inputs.any_just_pressed() {
+        settings.bindings.rebind(action, binding);
+        rebinding.0 = None;
+    }
+}
+
+fn update_binding_buttons(
+    settings: Res<Settings>,
+    rebinding: Res<Rebinding>,
+    buttons: Query<(Ref<BindingButton>, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    let changed = settings.is_changed() || rebinding.is_changed();
+    for (button, children) in &buttons {
+        let BindingButton::Action(action) = *button else {
+            continue;
+        };
+        if !changed && !button.is_added() {
+            continue;
+        }
+        let bindings = if rebinding.0 == Some(action) {
+            "press a key or button".to_string()
+        } else {
+            settings
+                .bindings
+                .bindings(action)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut texts = texts.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.sections[0].value = format!("{}: {bindings}", action.name());
+        }
+    }
+}
+
+fn stop_rebinding(mut rebinding: ResMut<Rebinding>) {
+    rebinding.0 = None;
+}
@@ -2,8 +2,15 @@
 
 use bevy::prelude::*;
 
-use super::Screen;
-use crate::ui::prelude::*;
+use super::{settings::SettingsMenu, Screen};
+use crate::{
+    game::{
+        input::{Action, Actions},
+        mode::GameMode,
+        save::{save_exists, LoadGame},
+    },
+    ui::prelude::*,
+};
 
 pub(super) fn plugin(app: &mut App) {
     app.add_systems(OnEnter(Screen::Title), enter_title);
@@ -11,14 +18,17 @@ pub(super) fn plugin(app: &mut App) {
     app.register_type::<TitleAction>();
     app.add_systems(
         Update,
-        (handle_title_action, handle_keyboard_action).run_if(in_state(Screen::Title)),
+        (handle_title_action, handle_keyboard_action)
+            .run_if(in_state(Screen::Title).and_then(in_state(SettingsMenu::Closed))),
     );
 }
 
 #[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
 #[reflect(Component)]
 enum TitleAction {
-    Play,
+    NewGame(GameMode),
+    Continue,
+    Settings,
     Credits,
     /// Exit doesn't work well with embedded applications.
     #[cfg(not(target_family = "wasm"))]
@@ -30,7 +40,16 @@ fn enter_title(mut commands: Commands) {
         .ui_root()
         .insert(StateScoped(Screen::Title))
         .with_children(|children| {
-            children.button("Play").insert(TitleAction::Play);
+            if save_exists() {
+                children.button("Continue").insert(TitleAction::Continue);
+            }
+            children.label("New game");
+            for mode in [GameMode::Survival, GameMode::Creative] {
+                children
+                    .button(mode.name())
+                    .insert(TitleAction::NewGame(mode));
+            }
+            children.button("Settings").insert(TitleAction::Settings);
             children.button("Credits").insert(TitleAction::Credits);
 
             #[cfg(not(target_family = "wasm"))]
@@ -39,14 +58,24 @@ fn enter_title(mut commands: Commands) {
 }
 
 fn handle_title_action(
+    mut commands: Commands,
     mut next_screen: ResMut<NextState<Screen>>,
+    mut next_menu: ResMut<NextState<SettingsMenu>>,
     mut button_query: InteractionQuery<&TitleAction>,
     #[cfg(not(target_family = "wasm"))] mut app_exit: EventWriter<AppExit>,
 ) {
     for (interaction, action) in &mut button_query {
         if matches!(interaction, Interaction::Pressed) {
             match action {
-                TitleAction::Play => next_screen.set(Screen::HexMap),
+                TitleAction::NewGame(mode) => {
+                    commands.trigger(LoadGame::New(*mode));
+                    next_screen.set(Screen::HexMap);
+                }
+                TitleAction::Continue => {
+                    commands.trigger(LoadGame::Saved);
+                    next_screen.set(Screen::HexMap);
+                }
+                TitleAction::Settings => next_menu.set(SettingsMenu::Open),
                 TitleAction::Credits => next_screen.set(Screen::Credits),
 
                 #[cfg(not(target_family = "wasm"))]
@@ -59,23 +88,12 @@ fn handle_title_action(
 }
 
 fn handle_keyboard_action(
-    mut next_screen: ResMut<NextState<Screen>>,
-    input: Res<ButtonInput<KeyCode>>,
+    actions: Actions,
     #[cfg(not(target_family = "wasm"))] mut app_exit: EventWriter<AppExit>,
 ) {
-    for key in (*input).get_just_pressed() {
-        match key {
-            KeyCode::Escape => {
-                #[cfg(not(target_family = "wasm"))]
-                app_exit.send(AppExit::Success);
-            }
-            KeyCode::KeyP => {
-                next_screen.set(Screen::HexMap);
-            }
-            KeyCode::KeyC => {
-                next_screen.set(Screen::Credits);
-            }
-            _ => {}
-        }
+    // Back on a gamepad is pressed too casually to quit the game on.
+    if actions.key_just_pressed(Action::Back) {
+        #[cfg(not(target_family = "wasm"))]
+        app_exit.send(AppExit::Success);
     }
 }
@@ -0,0 +1,78 @@
+//! The creative inventory: one slot per registered block, plus a slot holding the interior of
+//! another cell to place as a voxel.
+
+use bevy::prelude::*;
+
+use super::{
+    interiors::Interiors,
+    inventory::{Inventory, InventorySlot},
+    registry::BlockRegistry,
+    BlockType,
+};
+use crate::{
+    game::{
+        input::{action_just_pressed, Action},
+        mode::{in_mode, GameMode},
+    },
+    screen::Screen,
+    AppSet,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        stock_inventory.run_if(
+            in_mode(GameMode::Creative)
+                .and_then(resource_exists::<BlockRegistry>)
+                .and_then(resource_changed::<GameMode>.or_else(resource_changed::<BlockRegistry>)),
+        ),
+    );
+    app.add_systems(
+        Update,
+        pick_voxel.in_set(AppSet::Update).run_if(
+            in_state(Screen::VoxelWorld)
+                .and_then(in_mode(GameMode::Creative))
+                .and_then(action_just_pressed(Action::PickVoxel)),
+        ),
+    );
+}
+
+/// Fill the inventory with every registered block. The last slot is kept for voxels.
+fn stock_inventory(mut inventory: ResMut<Inventory>, registry: Res<BlockRegistry>) {
+    let voxel = inventory
+        .slots
+        .last()
+        .and_then(|slot| slot.resource_type.clone())
+        .filter(|block| matches!(block, BlockType::Voxel(_)));
+    inventory.slots = registry
+        .iter()
+        .map(|(id, _)| Some(BlockType::Block(id)))
+        .chain([voxel])
+        .map(|resource_type| InventorySlot {
+            quantity: resource_type.is_some().into(),
+            resource_type,
+        })
+        .collect();
+    inventory.selected = inventory.selected.min(inventory.slots.len() - 1);
+}
+
+/// Put the interior of the next cell in the voxel slot and select it.
+/// Cells are cycled through in a fixed order, so every one of them can be reached.
+fn pick_voxel(mut inventory: ResMut<Inventory>, interiors: Res<Interiors>, mut next: Local<usize>) {
+    let mut cells: Vec<_> = interiors.iter().collect();
+    if cells.is_empty() {
+        return;
+    }
+    cells.sort_by_key(|(id, _)| (id.q(), id.r()));
+    let (_, interior) = cells[*next % cells.len()];
+    *next = (*next + 1) % cells.len();
+
+    let Some(index) = inventory.slots.len().checked_sub(1) else {
+        return;
+    };
+    inventory.slots[index] = InventorySlot {
+        resource_type: Some(BlockType::Voxel(interior.clone())),
+        quantity: 1,
+    };
+    inventory.selected = index;
+}
@@ -0,0 +1,220 @@
+//! Blocks that fall when there is nothing under them, like sand and gravel.
+//! Unsupported blocks are taken out of the interior and fall as physics bodies,
+//! then settle back into the grid where they land.
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_rapier3d::prelude::*;
+
+use super::{
+    interiors::Interiors,
+    light::{LightField, LightSet},
+    mesh::interior_mesh,
+    registry::{BlockRegistry, BlockSound},
+    voxel_util::Blocks,
+    BlockType, VoxelData,
+};
+use crate::{
+    game::audio::sfx::PlaySfx,
+    screen::{HexSelect, Screen},
+    AppSet,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        OnEnter(Screen::VoxelWorld),
+        drop_unsupported_blocks
+            .run_if(resource_exists::<Blocks>.and_then(resource_exists::<BlockRegistry>)),
+    );
+    app.add_systems(OnExit(Screen::VoxelWorld), settle_all_blocks);
+    app.add_systems(
+        Update,
+        (
+            // Before the interior is relit and remeshed, so it no longer collides with the
+            // blocks that start falling.
+            drop_unsupported_blocks
+                .before(LightSet)
+                .run_if(resource_changed::<Interiors>),
+            settle_blocks,
+        )
+            .in_set(AppSet::Update)
+            .run_if(
+                in_state(Screen::VoxelWorld)
+                    .and_then(resource_exists::<Blocks>)
+                    .and_then(resource_exists::<BlockRegistry>),
+            ),
+    );
+}
+
+/// A block falling through the interior the player is in.
+#[derive(Component)]
+struct FallingBlock(BlockType);
+
+/// Blocks fall through Air and fluids.
+fn is_passable(block: Option<&BlockType>) -> bool {
+    matches!(block, Some(BlockType::Air | BlockType::Fluid(_)))
+}
+
+/// Where a block falling from `pos` comes to rest: the first position with something under it.
+/// The bottom of the interior holds blocks up.
+fn landing(interior: &VoxelData, mut pos: IVec3) -> IVec3 {
+    while pos.y > 0 && is_passable(interior.get(pos - IVec3::Y)) {
+        pos.y -= 1;
+    }
+    pos
+}
+
+fn drop_unsupported_blocks(
+    mut commands: Commands,
+    hex_select: Res<HexSelect>,
+    mut interiors: ResMut<Interiors>,
+    registry: Res<BlockRegistry>,
+    blocks: Res<Blocks>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut checked: Local<Option<VoxelData>>,
+    // Every falling block of a kind looks the same.
+    mut cubes: Local<HashMap<BlockType, Handle<Mesh>>>,
+) {
+    let Some(interior) = interiors.get(hex_select.hex_id) else {
+        return;
+    };
+    if checked
+        .as_ref()
+        .is_some_and(|checked| checked.shares_blocks(interior))
+    {
+        return;
+    }
+    *checked = Some(interior.clone());
+    let unsupported: Vec<_> = interior
+        .iter()
+        .filter(|(pos, block)| registry.falls(block) && landing(interior, *pos) != *pos)
+        .map(|(pos, block)| (pos, block.clone()))
+        .collect();
+    if unsupported.is_empty() {
+        return;
+    }
+
+    // The atlas is laid out again when the registry is reloaded.
+    if blocks.is_changed() {
+        cubes.clear();
+    }
+    let Some(interior) = interiors.get_mut(hex_select.hex_id) else {
+        return;
+    };
+    for (pos, block) in unsupported {
+        interior.set(pos, BlockType::Air);
+        let mesh = cubes
+            .entry(block.clone())
+            .or_insert_with(|| {
+                // The falling block is rendered like an interior holding nothing else.
+                let mut alone = VoxelData::default();
+                alone.set(IVec3::ZERO, block.clone());
+                let light = LightField::compute(&alone, &*registry);
+                meshes.add(interior_mesh(&alone, &blocks, &registry, &light))
+            })
+            .clone();
+        commands.spawn((
+            Name::new("Falling Block"),
+            FallingBlock(block),
+            StateScoped(Screen::VoxelWorld),
+            PbrBundle {
+                mesh,
+                material: blocks.material(),
+                transform: Transform::from_translation(pos.as_vec3()),
+                ..default()
+            },
+            RigidBody::Dynamic,
+            // Slightly smaller than a block, so it doesn't catch on the walls of a shaft.
+            Collider::cuboid(0.49, 0.49, 0.49),
+            LockedAxes::ROTATION_LOCKED
+                | LockedAxes::TRANSLATION_LOCKED_X
+                | LockedAxes::TRANSLATION_LOCKED_Z,
+        ));
+    }
+}
+
+/// How close to the middle of a block a falling block has to be to settle there.
+const SETTLE_DISTANCE: f32 = 0.05;
+
+/// Put blocks that have landed back into the grid.
+fn settle_blocks(
+    mut commands: Commands,
+    falling: Query<(Entity, &FallingBlock, &Transform)>,
+    hex_select: Res<HexSelect>,
+    mut interiors: ResMut<Interiors>,
+) {
+    for (entity, FallingBlock(block), transform) in &falling {
+        let Some(interior) = interiors.get(hex_select.hex_id) else {
+            return;
+        };
+        let pos = transform.translation.round().as_ivec3();
+        // Blocks that fall out of the bottom of the interior land on it instead.
+        let fell_out = pos.y < 0;
+        let resting = fell_out
+            || ((transform.translation.y - pos.y as f32).abs() < SETTLE_DISTANCE
+                && landing(interior, pos) == pos);
+        if !resting {
+            continue;
+        }
+        commands.entity(entity).despawn_recursive();
+        let pos = IVec3::new(pos.x, pos.y.max(0), pos.z);
+        settle(
+            &mut commands,
+            &mut interiors,
+            &hex_select,
+            pos,
+            block.clone(),
+        );
+    }
+}
+
+/// Land every block that is still falling when the player leaves, so none are lost.
+fn settle_all_blocks(
+    mut commands: Commands,
+    falling: Query<(&FallingBlock, &Transform)>,
+    hex_select: Res<HexSelect>,
+    mut interiors: ResMut<Interiors>,
+) {
+    for (FallingBlock(block), transform) in &falling {
+        let Some(interior) = interiors.get(hex_select.hex_id) else {
+            return;
+        };
+        let pos = transform.translation.round().as_ivec3();
+        let pos = landing(interior, IVec3::new(pos.x, pos.y.max(0), pos.z));
+        settle(
+            &mut commands,
+            &mut interiors,
+            &hex_select,
+            pos,
+            block.clone(),
+        );
+    }
+}
+
+/// Puts `block` back into the grid at `pos`, or in the first free position above it
+/// if something else took its place while it fell.
+fn settle(
+    commands: &mut Commands,
+    interiors: &mut Interiors,
+    hex_select: &HexSelect,
+    mut pos: IVec3,
+    block: BlockType,
+) {
+    let Some(interior) = interiors.get_mut(hex_select.hex_id) else {
+        return;
+    };
+    loop {
+        match interior.get(pos) {
+            None => return,
+            Some(BlockType::Air | BlockType::Fluid(_)) => break,
+            Some(_) => pos.y += 1,
+        }
+    }
+    if let Some(id) = block.appearance() {
+        commands.trigger(PlaySfx::Block {
+            block: id,
+            sound: BlockSound::Place,
+            position: pos.as_vec3(),
+        });
+    }
+    interior.set(pos, block);
+}
@@ -0,0 +1,335 @@
+//! Water, lava and other fluids flowing through interiors.
+//! Fluids are a cellular automaton stepped by the simulation: they fall first, then spread sideways
+//! from where they rest, losing some level with every block. Flowing fluid dries up once it is cut
+//! off from its source. Fluids flow through the faces of an interior into the neighbouring cell,
+//! and turn into blocks when they touch other fluids, like lava into stone next to water.
+
+use std::cmp::Reverse;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use super::{
+    interiors::Interiors,
+    registry::{BlockId, BlockRegistry, FluidDefinition},
+    BlockType,
+};
+use crate::screen::{hex_map::cells::HexId, MapDirection};
+
+/// Level of sources and of fluid falling straight down.
+pub const MAX_FLUID_LEVEL: u8 = 8;
+
+/// Some amount of a registered fluid.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct Fluid {
+    pub id: BlockId,
+    /// From [`MAX_FLUID_LEVEL`] down to 1 at the far end of a flow.
+    pub level: u8,
+    /// Sources never dry up. They are made by placing a fluid.
+    pub source: bool,
+}
+
+impl Fluid {
+    pub fn source(id: BlockId) -> Self {
+        Fluid {
+            id,
+            level: MAX_FLUID_LEVEL,
+            source: true,
+        }
+    }
+}
+
+/// Whether `fluid` flows on this step.
+fn is_due(fluid: &FluidDefinition, step: u64) -> bool {
+    step % u64::from(fluid.delay.max(1)) == 0
+}
+
+/// Flows the fluids of the interior of `id` by one step of the [simulation](super::simulation).
+/// Neighbouring interiors are read as they are, whether they already took this step or not.
+pub(super) fn flow(interiors: &mut Interiors, registry: &BlockRegistry, id: HexId, step: u64) {
+    let is_wet = |id: HexId| {
+        interiors.get(id).is_some_and(|interior| {
+            interior
+                .iter()
+                .any(|(_, block)| matches!(block, BlockType::Fluid(_)))
+        })
+    };
+    // Fluid can only appear next to fluid, which may be in the neighbouring cell.
+    if !is_wet(id) && !MapDirection::iter().any(|direction| is_wet(id + direction)) {
+        return;
+    }
+    let Some(interior) = interiors.get(id) else {
+        return;
+    };
+    let changes: Vec<_> = interior
+        .iter()
+        .filter_map(|(pos, block)| {
+            let next = next_block(interiors, registry, step, id, pos, block)?;
+            (next != *block).then_some((pos, next))
+        })
+        .collect();
+    if changes.is_empty() {
+        return;
+    }
+    if let Some(interior) = interiors.get_mut(id) {
+        for (pos, block) in changes {
+            interior.set(pos, block);
+        }
+    }
+}
+
+/// What `block` at `pos` becomes after this step, or `None` if it stays the same.
+fn next_block(
+    interiors: &Interiors,
+    registry: &BlockRegistry,
+    step: u64,
+    id: HexId,
+    pos: IVec3,
+    block: &BlockType,
+) -> Option<BlockType> {
+    let at = |offset: IVec3| interiors.block_at(id, pos + offset);
+    match block {
+        BlockType::Fluid(fluid) => {
+            let definition = registry.fluid(fluid.id)?;
+            for offset in NEIGHBOURS {
+                let Some(BlockType::Fluid(other)) = at(offset) else {
+                    continue;
+                };
+                let reaction = (other.id != fluid.id)
+                    .then(|| registry.get(other.id))
+                    .flatten()
+                    .and_then(|other| definition.reactions.get(&other.name));
+                if let Some(block) = reaction.and_then(|name| registry.block(name)) {
+                    return Some(block);
+                }
+            }
+            if fluid.source || !is_due(definition, step) {
+                return None;
+            }
+            let level = inflow(&at, fluid.id, definition.falloff);
+            Some(level.map_or(BlockType::Air, |level| {
+                BlockType::Fluid(Fluid {
+                    id: fluid.id,
+                    level,
+                    source: false,
+                })
+            }))
+        }
+        // The fluid flowing in the most fills the gap.
+        BlockType::Air => FEEDERS
+            .iter()
+            .filter_map(|offset| match at(*offset) {
+                Some(BlockType::Fluid(fluid)) => Some(fluid.id),
+                _ => None,
+            })
+            .filter_map(|fluid| {
+                let definition = registry
+                    .fluid(fluid)
+                    .filter(|definition| is_due(definition, step))?;
+                Some((inflow(&at, fluid, definition.falloff)?, fluid))
+            })
+            .max_by_key(|(level, fluid)| (*level, Reverse(*fluid)))
+            .map(|(level, id)| {
+                BlockType::Fluid(Fluid {
+                    id,
+                    level,
+                    source: false,
+                })
+            }),
+        _ => None,
+    }
+}
+
+const NEIGHBOURS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+/// Directions fluid can flow in from: above, and every side.
+const FEEDERS: [IVec3; 5] = [IVec3::Y, IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z];
+
+/// Level of `fluid` flowing into a position, given the blocks around it.
+fn inflow<'a>(
+    at: &impl Fn(IVec3) -> Option<&'a BlockType>,
+    fluid: BlockId,
+    falloff: u8,
+) -> Option<u8> {
+    let is_fluid = |block: Option<&BlockType>| matches!(block, Some(BlockType::Fluid(other)) if other.id == fluid);
+    if is_fluid(at(IVec3::Y)) {
+        return Some(MAX_FLUID_LEVEL);
+    }
+    FEEDERS[1..]
+        .iter()
+        .filter_map(|offset| match at(*offset) {
+            Some(BlockType::Fluid(side)) if side.id == fluid => Some((offset, side)),
+            _ => None,
+        })
+        // Fluid that can still fall doesn't spread, unless it's a source.
+        .filter(|(offset, side)| {
+            let below = at(**offset + IVec3::NEG_Y);
+            side.source || !(below == Some(&BlockType::Air) || is_fluid(below))
+        })
+        .map(|(_, side)| side.level.saturating_sub(falloff))
+        .filter(|level| *level > 0)
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen::voxel_world::{VoxelData, VOXEL_DIVISION_FACTOR};
+
+    fn test_registry() -> BlockRegistry {
+        BlockRegistry::from_ron(
+            r#"(blocks: [
+                (name: "stone"),
+                (name: "water", solid: false, fluid: (falloff: 1)),
+                (name: "lava", solid: false, fluid: (falloff: 2, reactions: {"water": "stone"})),
+            ])"#,
+        )
+    }
+
+    /// An interior with a stone floor and nothing else.
+    fn floored(registry: &BlockRegistry) -> VoxelData {
+        let mut interior = VoxelData::default();
+        let size = VOXEL_DIVISION_FACTOR as i32;
+        for x in 0..size {
+            for z in 0..size {
+                interior.set(IVec3::new(x, 0, z), registry.block("stone").unwrap());
+            }
+        }
+        interior
+    }
+
+    fn fluid_at(interiors: &Interiors, id: HexId, pos: IVec3) -> Option<(u8, bool)> {
+        match interiors.block_at(id, pos)? {
+            BlockType::Fluid(fluid) => Some((fluid.level, fluid.source)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn fluid_falls_before_spreading() {
+        let registry = test_registry();
+        let water = registry.id("water").unwrap();
+        let id = HexId::new(0, 0);
+        let mut interiors = Interiors::default();
+        let mut interior = floored(&registry);
+        // A flowing block in mid air, fed by a source above it.
+        interior.set(IVec3::new(8, 9, 8), BlockType::Fluid(Fluid::source(water)));
+        interior.set(
+            IVec3::new(8, 8, 8),
+            BlockType::Fluid(Fluid {
+                id: water,
+                level: 5,
+                source: false,
+            }),
+        );
+        interiors.insert(id, interior);
+        flow(&mut interiors, &registry, id, 0);
+        // Fluid with more above it is full, and falls into the Air below it rather than spreading.
+        assert_eq!(
+            fluid_at(&interiors, id, IVec3::new(8, 8, 8)),
+            Some((8, false))
+        );
+        assert_eq!(
+            fluid_at(&interiors, id, IVec3::new(8, 7, 8)),
+            Some((8, false))
+        );
+        assert_eq!(fluid_at(&interiors, id, IVec3::new(9, 8, 8)), None);
+    }
+
+    #[test]
+    fn fluid_levels_out_and_dries_up() {
+        let registry = test_registry();
+        let water = registry.id("water").unwrap();
+        let id = HexId::new(0, 0);
+        let source = IVec3::new(8, 1, 8);
+        let mut interiors = Interiors::default();
+        let mut interior = floored(&registry);
+        interior.set(source, BlockType::Fluid(Fluid::source(water)));
+        interiors.insert(id, interior);
+        for step in 0..20 {
+            flow(&mut interiors, &registry, id, step);
+        }
+        // Every block away from the source loses one level, down to nothing.
+        assert_eq!(
+            fluid_at(&interiors, id, source),
+            Some((MAX_FLUID_LEVEL, true))
+        );
+        for distance in 1..MAX_FLUID_LEVEL as i32 {
+            let level = MAX_FLUID_LEVEL - distance as u8;
+            let pos = source + IVec3::new(distance, 0, 0);
+            assert_eq!(fluid_at(&interiors, id, pos), Some((level, false)));
+        }
+        assert_eq!(fluid_at(&interiors, id, source + IVec3::Y), None);
+
+        interiors.get_mut(id).unwrap().set(source, BlockType::Air);
+        for step in 20..40 {
+            flow(&mut interiors, &registry, id, step);
+        }
+        let interior = interiors.get(id).unwrap();
+        assert!(!interior
+            .iter()
+            .any(|(_, block)| matches!(block, BlockType::Fluid(_))));
+    }
+
+    #[test]
+    fn lava_touching_water_turns_into_stone() {
+        let registry = test_registry();
+        let id = HexId::new(0, 0);
+        let lava = IVec3::new(5, 1, 5);
+        let mut interiors = Interiors::default();
+        let mut interior = floored(&registry);
+        interior.set(
+            lava,
+            BlockType::Fluid(Fluid::source(registry.id("lava").unwrap())),
+        );
+        interior.set(
+            lava + IVec3::X,
+            BlockType::Fluid(Fluid::source(registry.id("water").unwrap())),
+        );
+        interiors.insert(id, interior);
+        flow(&mut interiors, &registry, id, 0);
+        assert_eq!(
+            interiors.block_at(id, lava),
+            registry.block("stone").as_ref()
+        );
+        // Water has no reaction of its own.
+        assert_eq!(
+            fluid_at(&interiors, id, lava + IVec3::X),
+            Some((MAX_FLUID_LEVEL, true))
+        );
+    }
+
+    #[test]
+    fn fluid_flows_across_faces() {
+        let registry = test_registry();
+        let water = registry.id("water").unwrap();
+        let id = HexId::new(0, 0);
+        let neighbour = id + MapDirection::from_face_normal(IVec3::X).unwrap();
+        let mut interiors = Interiors::default();
+        let mut interior = floored(&registry);
+        interior.set(IVec3::new(15, 1, 8), BlockType::Fluid(Fluid::source(water)));
+        interiors.insert(id, interior);
+        interiors.insert(neighbour, floored(&registry));
+        flow(&mut interiors, &registry, neighbour, 0);
+        assert_eq!(
+            fluid_at(&interiors, neighbour, IVec3::new(0, 1, 8)),
+            Some((MAX_FLUID_LEVEL - 1, false))
+        );
+        // Flowing into a cell that was never generated does nothing.
+        let unexplored = id + MapDirection::from_face_normal(IVec3::NEG_X).unwrap();
+        let mut interior = floored(&registry);
+        interior.set(IVec3::new(0, 1, 8), BlockType::Fluid(Fluid::source(water)));
+        interiors.insert(id, interior);
+        flow(&mut interiors, &registry, id, 0);
+        flow(&mut interiors, &registry, unexplored, 0);
+        assert!(interiors.get(unexplored).is_none());
+    }
+}
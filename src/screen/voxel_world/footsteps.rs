@@ -0,0 +1,73 @@
+//! Footstep sounds of the block the player walks on.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::KinematicCharacterControllerOutput;
+
+use super::{
+    interiors::Interiors,
+    player_controller::Flight,
+    registry::{BlockRegistry, BlockSound},
+    BlockType,
+};
+use crate::{
+    game::audio::sfx::PlaySfx,
+    screen::{HexSelect, Screen},
+    AppSet,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        play_footsteps
+            .in_set(AppSet::Update)
+            .run_if(in_state(Screen::VoxelWorld).and_then(resource_exists::<BlockRegistry>)),
+    );
+}
+
+/// Horizontal distance walked between two footsteps.
+const STEP_LENGTH: f32 = 1.8;
+
+/// Distance from the center of the player's body to just below its collider.
+const FEET_OFFSET: f32 = 1.;
+
+/// Plays footsteps as the entity walks, with the distance walked since the last one.
+#[derive(Component, Default)]
+pub struct Footsteps(f32);
+
+fn play_footsteps(
+    mut commands: Commands,
+    mut walkers: Query<(
+        &mut Footsteps,
+        &KinematicCharacterControllerOutput,
+        &GlobalTransform,
+    )>,
+    hex_select: Res<HexSelect>,
+    interiors: Res<Interiors>,
+    flight: Res<Flight>,
+) {
+    if flight.flying {
+        return;
+    }
+    for (mut footsteps, output, transform) in &mut walkers {
+        if !output.grounded {
+            continue;
+        }
+        footsteps.0 += output.effective_translation.xz().length();
+        if footsteps.0 < STEP_LENGTH {
+            continue;
+        }
+        footsteps.0 = 0.;
+
+        let feet = transform.translation() - Vec3::Y * FEET_OFFSET;
+        let ground = interiors
+            .get(hex_select.hex_id)
+            .and_then(|interior| interior.get(feet.round().as_ivec3()));
+        if let Some(BlockType::Block(id)) = ground {
+            commands.trigger(PlaySfx::Block {
+                block: *id,
+                sound: BlockSound::Step,
+                position: feet,
+            });
+        }
+    }
+}
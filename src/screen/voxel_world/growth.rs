@@ -0,0 +1,59 @@
+//! Blocks that grow into other blocks over time, like saplings into wood.
+//! Every step of the simulation picks a few random positions of an interior, and the growing
+//! blocks among them may grow if there is enough light above them.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use super::{
+    interiors::Interiors, light::InteriorLights, registry::BlockRegistry, VOXEL_DIVISION_FACTOR,
+};
+use crate::screen::hex_map::cells::HexId;
+
+/// Positions picked in an interior every step.
+const GROWTH_PICKS: usize = 16;
+
+const SIZE: i32 = VOXEL_DIVISION_FACTOR as i32;
+
+/// Grows the blocks of the interior of `id` by one step of the [simulation](super::simulation).
+pub(super) fn grow(
+    interiors: &mut Interiors,
+    lights: &mut InteriorLights,
+    registry: &BlockRegistry,
+    id: HexId,
+    rng: &mut impl Rng,
+) {
+    let Some(interior) = interiors.get(id) else {
+        return;
+    };
+    let mut grown = Vec::new();
+    for _ in 0..GROWTH_PICKS {
+        let pos = IVec3::new(
+            rng.gen_range(0..SIZE),
+            rng.gen_range(0..SIZE),
+            rng.gen_range(0..SIZE),
+        );
+        let Some(growth) = interior.get(pos).and_then(|block| registry.growth(block)) else {
+            continue;
+        };
+        if !rng.gen_bool(growth.chance.clamp(0., 1.).into()) {
+            continue;
+        }
+        // The light reaching a block comes from above it.
+        let light = lights.sync(id, interior, registry);
+        if light.get(pos + IVec3::Y) < growth.light {
+            continue;
+        }
+        if let Some(block) = registry.block(&growth.into) {
+            grown.push((pos, block));
+        }
+    }
+    if grown.is_empty() {
+        return;
+    }
+    if let Some(interior) = interiors.get_mut(id) {
+        for (pos, block) in grown {
+            interior.set(pos, block);
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! Placing blocks in the voxel world.
+//! The player targets the first non-Air block in front of the camera
+//! and places blocks from the selected slot against it. Breaking them is in [`super::mining`].
+//! In creative, blocks are never used up.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::KinematicCharacterController;
+
+use super::{
+    interiors::Interiors,
+    inventory::Inventory,
+    machines::{facing, Machine, Machines},
+    registry::{BlockRegistry, BlockSound},
+    voxel_util::VoxelPlayer,
+    BlockType, VoxelData,
+};
+use crate::{
+    game::{
+        audio::sfx::PlaySfx,
+        input::{Action, Actions},
+        mode::GameMode,
+    },
+    screen::{HexSelect, Screen},
+    AppSet,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (select_slot, place_block)
+            .chain()
+            .in_set(AppSet::Update)
+            .run_if(in_state(Screen::VoxelWorld).and_then(resource_exists::<BlockRegistry>)),
+    );
+}
+
+/// How far away from the camera blocks can be reached, in blocks.
+const REACH: f32 = 5.;
+
+/// Half extents of a box around the player's collider. Blocks can't be placed inside of it.
+const PLAYER_HALF_EXTENTS: Vec3 = Vec3::new(0.45, 0.95, 0.45);
+
+/// A block hit by a ray.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RayHit {
+    pub pos: IVec3,
+    /// Normal of the face the ray entered the block through.
+    /// Zero when the ray starts inside the block.
+    pub normal: IVec3,
+}
+
+/// Walks the grid along a ray and returns the first block that isn't Air or a fluid
+/// within `max_distance`.
+/// Blocks are unit cubes centered on their position.
+pub fn raycast(
+    interior: &VoxelData,
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+) -> Option<RayHit> {
+    let direction = direction.try_normalize()?;
+    // Shifted so that the block at `pos` covers `pos..pos + 1` on every axis.
+    let origin = origin + Vec3::splat(0.5);
+    let mut pos = origin.floor().as_ivec3();
+    // Distance along the ray to cross a whole block on each axis.
+    let delta = direction.recip().abs();
+    // Distance along the ray to the next block boundary on each axis.
+    let mut next = Vec3::INFINITY;
+    let mut step = IVec3::ZERO;
+    for axis in 0..3 {
+        if direction[axis] > 0. {
+            step[axis] = 1;
+            next[axis] = (pos[axis] as f32 + 1. - origin[axis]) * delta[axis];
+        } else if direction[axis] < 0. {
+            step[axis] = -1;
+            next[axis] = (origin[axis] - pos[axis] as f32) * delta[axis];
+        }
+    }
+
+    let mut normal = IVec3::ZERO;
+    let mut distance = 0.;
+    while distance <= max_distance {
+        if interior
+            .get(pos)
+            .is_some_and(|block| !matches!(block, BlockType::Air | BlockType::Fluid(_)))
+        {
+            return Some(RayHit { pos, normal });
+        }
+        let axis = if next.x < next.y && next.x < next.z {
+            0
+        } else if next.y < next.z {
+            1
+        } else {
+            2
+        };
+        distance = next[axis];
+        next[axis] += delta[axis];
+        pos[axis] += step[axis];
+        normal = IVec3::ZERO;
+        normal[axis] = -step[axis];
+    }
+    None
+}
+
+/// The block the player is looking at in the current interior.
+pub(super) fn target(
+    camera: &Query<&GlobalTransform, With<VoxelPlayer>>,
+    interiors: &Interiors,
+    hex_select: &HexSelect,
+) -> Option<RayHit> {
+    let camera = camera.get_single().ok()?;
+    let interior = interiors.get(hex_select.hex_id)?;
+    raycast(
+        interior,
+        camera.translation(),
+        camera.forward().as_vec3(),
+        REACH,
+    )
+}
+
+fn select_slot(actions: Actions, mut inventory: ResMut<Inventory>) {
+    let slots = inventory.slots.len();
+    if actions.just_pressed(Action::NextSlot) {
+        inventory.selected = (inventory.selected + 1) % slots;
+    }
+    if actions.just_pressed(Action::PreviousSlot) {
+        inventory.selected = (inventory.selected + slots - 1) % slots;
+    }
+}
+
+fn place_block(
+    mut commands: Commands,
+    actions: Actions,
+    camera: Query<&GlobalTransform, With<VoxelPlayer>>,
+    players: Query<&GlobalTransform, With<KinematicCharacterController>>,
+    hex_select: Res<HexSelect>,
+    mut interiors: ResMut<Interiors>,
+    mut inventory: ResMut<Inventory>,
+    mut machines: ResMut<Machines>,
+    registry: Res<BlockRegistry>,
+    mode: Res<GameMode>,
+) {
+    if !actions.just_pressed(Action::Place) {
+        return;
+    }
+    let Some(block) = inventory.selected_slot().resource_type.clone() else {
+        return;
+    };
+    if !registry.is_placeable(&block) {
+        return;
+    }
+    let Some(hit) = target(&camera, &interiors, &hex_select) else {
+        return;
+    };
+    let pos = hit.pos + hit.normal;
+    // Fluids make way for blocks.
+    let is_free = matches!(
+        interiors
+            .get(hex_select.hex_id)
+            .and_then(|interior| interior.get(pos)),
+        Some(BlockType::Air | BlockType::Fluid(_))
+    );
+    let overlaps_player = players.iter().any(|player| {
+        (pos.as_vec3() - player.translation())
+            .abs()
+            .cmplt(PLAYER_HALF_EXTENTS + 0.5)
+            .all()
+    });
+    if !is_free || overlaps_player {
+        return;
+    }
+    if *mode == GameMode::Survival && !inventory.check_and_deduct_resources(&[(block.clone(), 1)]) {
+        return;
+    }
+    if let Some(id) = block.appearance() {
+        commands.trigger(PlaySfx::Block {
+            block: id,
+            sound: BlockSound::Place,
+            position: pos.as_vec3(),
+        });
+    }
+    // Machines face away from the player.
+    if let (Some(definition), Ok(camera)) = (registry.machine(&block), camera.get_single()) {
+        let machine = Machine::new(definition, facing(definition, camera.forward().as_vec3()));
+        machines.insert(hex_select.hex_id, pos, machine);
+    }
+    if let Some(interior) = interiors.get_mut(hex_select.hex_id) {
+        interior.set(pos, registry.placed(block));
+    }
+}
@@ -0,0 +1,200 @@
+//! Storage for the voxel interior of every hex cell.
+//! Interiors outlive the voxel world screen so that changes made from the hex map
+//! and from inside the cell are applied to the same data.
+
+use bevy::{prelude::*, utils::HashMap};
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    registry::{BlockId, BlockRegistry},
+    BlockType, VoxelData, VOXEL_DIVISION_FACTOR,
+};
+use crate::screen::{
+    hex_map::cells::{HexId, HexagonType},
+    MapDirection,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<Interiors>();
+}
+
+/// The interiors of all hex cells that have been generated so far.
+#[derive(Resource, Default, Clone, Serialize, Deserialize)]
+pub struct Interiors(HashMap<HexId, VoxelData>);
+
+impl Interiors {
+    pub fn get(&self, id: HexId) -> Option<&VoxelData> {
+        self.0.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: HexId) -> Option<&mut VoxelData> {
+        self.0.get_mut(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (HexId, &VoxelData)> {
+        self.0.iter().map(|(id, interior)| (*id, interior))
+    }
+
+    /// Sets the interior of `id`, for tests.
+    #[cfg(test)]
+    pub(super) fn insert(&mut self, id: HexId, interior: VoxelData) {
+        self.0.insert(id, interior);
+    }
+
+    /// The block at `pos` in the interior of `id`, following [`resolve`] across faces.
+    pub fn block_at(&self, id: HexId, pos: IVec3) -> Option<&BlockType> {
+        let (id, pos) = resolve(id, pos)?;
+        self.get(id)?.get(pos)
+    }
+
+    /// Replaces blocks missing from `registry` with Air, returning how many there were.
+    pub fn forget_unknown_blocks(&mut self, registry: &BlockRegistry) -> usize {
+        let mut forgotten = 0;
+        for interior in self.0.values_mut() {
+            let unknown: Vec<_> = interior
+                .iter()
+                .filter(|(_, block)| !registry.knows(block))
+                .map(|(pos, _)| pos)
+                .collect();
+            forgotten += unknown.len();
+            for pos in unknown {
+                interior.set(pos, BlockType::Air);
+            }
+        }
+        forgotten
+    }
+
+    /// Returns the interior of `id`, generating it from `hex_type` the first time it is requested.
+    pub fn get_or_generate(
+        &mut self,
+        id: HexId,
+        hex_type: HexagonType,
+        registry: &BlockRegistry,
+    ) -> &mut VoxelData {
+        self.0
+            .entry(id)
+            .or_insert_with(|| WorldType::new(hex_type, registry).generate(id))
+    }
+}
+
+/// Finds the cell and position of `pos` in the interior of `id`. Positions past a face of the
+/// interior are in the neighbouring cell in the [`MapDirection`] of that face.
+/// Returns `None` past edges and corners, where no single cell is next to the interior.
+pub fn resolve(id: HexId, pos: IVec3) -> Option<(HexId, IVec3)> {
+    let size = VOXEL_DIVISION_FACTOR as i32;
+    let normal = IVec3::select(
+        pos.cmplt(IVec3::ZERO),
+        IVec3::NEG_ONE,
+        IVec3::select(pos.cmpge(IVec3::splat(size)), IVec3::ONE, IVec3::ZERO),
+    );
+    if normal == IVec3::ZERO {
+        return Some((id, pos));
+    }
+    let direction = MapDirection::from_face_normal(normal)?;
+    let pos = pos - normal * size;
+    VoxelData::index(pos).map(|_| (id + direction, pos))
+}
+
+/// Name of the block interiors are made of when nothing else is there.
+const BASE_BLOCK: &str = "stone";
+/// Name of the block sprinkled over the floor of empty cells.
+const FLOOR_ORE: &str = "coal";
+
+enum WorldType {
+    /// A floor of `base` with some `ore` lying on top of it.
+    Flat { base: BlockType, ore: BlockType },
+    /// A mostly solid cave made of `base`, rich in `resource`.
+    Deposit { base: BlockType, resource: BlockId },
+}
+
+impl WorldType {
+    fn new(hex_type: HexagonType, registry: &BlockRegistry) -> Self {
+        let base = registry.block(BASE_BLOCK).unwrap_or(BlockType::Air);
+        match hex_type {
+            // Empty cells still get a floor so the player has something to stand on.
+            // Voxel cells only come from interiors that already exist.
+            HexagonType::Empty | HexagonType::Voxel => WorldType::Flat {
+                base,
+                ore: registry.block(FLOOR_ORE).unwrap_or(BlockType::Air),
+            },
+            HexagonType::Block(resource) => WorldType::Deposit { base, resource },
+        }
+    }
+
+    fn generate(&self, id: HexId) -> VoxelData {
+        let mut data = VoxelData::default();
+        let seed = (id.q() as u32 as u64) << 32 | id.r() as u32 as u64;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        let size = VOXEL_DIVISION_FACTOR as i32;
+        for x in 0..size {
+            for y in 0..size {
+                for z in 0..size {
+                    let pos = IVec3::new(x, y, z);
+                    data.set(pos, self.sample(&mut rng, pos));
+                }
+            }
+        }
+        data
+    }
+
+    fn sample(&self, mut rng: impl Rng, pos: IVec3) -> BlockType {
+        match self {
+            WorldType::Flat { base, ore } => {
+                if pos.y == 0 {
+                    base.clone()
+                } else if (pos.y == 1 || pos.y == 2) && rng.gen_bool(0.1) {
+                    ore.clone()
+                } else {
+                    BlockType::Air
+                }
+            }
+            WorldType::Deposit { base, resource } => {
+                if rng.gen_bool(0.3) && pos.y != 0 {
+                    BlockType::Air
+                } else if BlockType::Block(*resource) == *base || rng.gen_bool(0.25) {
+                    BlockType::Block(*resource)
+                } else {
+                    base.clone()
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn resolve_across_faces_only() {
+    use strum::IntoEnumIterator;
+
+    let id = HexId::new(2, -1);
+    let size = VOXEL_DIVISION_FACTOR as i32;
+    assert_eq!(
+        resolve(id, IVec3::new(3, 4, 5)),
+        Some((id, IVec3::new(3, 4, 5)))
+    );
+    for direction in MapDirection::iter() {
+        let normal = direction.face_normal();
+        // Just outside the middle of a face is just inside the opposite face of the neighbour.
+        let outside = IVec3::select(
+            normal.cmpeq(IVec3::ZERO),
+            IVec3::splat(size / 2),
+            IVec3::select(
+                normal.cmpgt(IVec3::ZERO),
+                IVec3::splat(size),
+                IVec3::NEG_ONE,
+            ),
+        );
+        assert_eq!(
+            resolve(id, outside),
+            Some((id + direction, outside - normal * size))
+        );
+    }
+    // Edges and corners don't lead anywhere.
+    for pos in [
+        IVec3::new(size, 1, size),
+        IVec3::new(-1, -1, 8),
+        IVec3::new(size, size, -1),
+    ] {
+        assert_eq!(resolve(id, pos), None);
+    }
+}
@@ -1,20 +1,29 @@
-use bevy::prelude::Component;
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
 
-use super::BlockType;
+use super::{registry::BlockRegistry, BlockType};
 
 /// Define a struct for inventory slots
 /// Fields are public to allow direct access from UI. This can be changed to getter in the future
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InventorySlot {
     pub resource_type: Option<BlockType>,
     pub quantity: u32,
 }
 
-/// This is the inventory component, meant to be used in conjunction with VoxelPlayer
+/// This is the player's inventory. It is a resource so that it is shared between the hex map and the voxel world
 /// Fields are public to allow direct access from UI. This can be changed to getter in the future
-#[derive(Component)]
+#[derive(Resource, Clone, Serialize, Deserialize)]
 pub struct Inventory {
     pub slots: Vec<InventorySlot>,
+    /// Index of the slot blocks are placed from.
+    pub selected: usize,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Inventory::new(10)
+    }
 }
 
 impl Inventory {
@@ -27,60 +36,174 @@ impl Inventory {
                 };
                 size
             ],
+            selected: 0,
         }
     }
 
-    pub fn add_resource(&mut self, resource_type: BlockType, quantity: u32) {
-        match self.slots.iter_mut().find(|slot| {
-            slot.resource_type == Some(resource_type.clone()) || slot.resource_type.is_none()
-        }) {
-            Some(slot) => {
-                slot.resource_type = Some(resource_type);
-                slot.quantity += quantity;
-            }
-            None => println!("Inventory full, couldn't add resource"),
-        }
+    pub fn selected_slot(&self) -> &InventorySlot {
+        &self.slots[self.selected]
+    }
+
+    /// Adds resources to the slots already holding them, then to empty ones, up to the stack size
+    /// of the resource. Adds nothing and returns `false` if they don't all fit.
+    pub fn add_resource(
+        &mut self,
+        resource_type: BlockType,
+        quantity: u32,
+        registry: &BlockRegistry,
+    ) -> bool {
+        let stack_size = registry.stack_size(&resource_type);
+        add_to_slots(&mut self.slots, resource_type, quantity, stack_size)
+    }
+
+    /// Empties the slots holding blocks missing from `registry`, returning how many there were.
+    pub fn forget_unknown_blocks(&mut self, registry: &BlockRegistry) -> usize {
+        forget_unknown_in_slots(&mut self.slots, registry)
     }
 
     pub fn get_total_resource(&self, resource_type: BlockType) -> u32 {
-        self.slots
-            .iter()
-            .filter(|slot| slot.resource_type == Some(resource_type.clone()))
-            .map(|slot| slot.quantity)
-            .sum()
+        total_in_slots(&self.slots, &resource_type)
     }
 
     // This method first checks to see if one has the resources for crafting.
     // If the inventory has those resources it then deducts those resources and returns true.
     // If the inventory does not it returns false
     pub fn check_and_deduct_resources(&mut self, requirements: &[(BlockType, u32)]) -> bool {
-        // First, check if we have enough of each resource
-        for (resource_type, required_amount) in requirements {
-            if self.get_total_resource((*resource_type).clone()) < *required_amount {
-                return false;
-            }
+        deduct_from_slots(&mut self.slots, requirements)
+    }
+}
+
+// The functions below work on any storage made of slots, like the player's inventory
+// or the storage of machines.
+
+/// How many more of `resource_type` fit in the slots, with at most `stack_size` in each.
+pub fn space_in_slots(slots: &[InventorySlot], resource_type: &BlockType, stack_size: u32) -> u32 {
+    slots
+        .iter()
+        .map(|slot| match &slot.resource_type {
+            Some(held) if held == resource_type => stack_size.saturating_sub(slot.quantity),
+            Some(_) => 0,
+            None => stack_size,
+        })
+        .sum()
+}
+
+/// Adds resources to the slots already holding them, then to empty ones, with at most
+/// `stack_size` in each. Adds nothing and returns `false` if they don't all fit.
+pub fn add_to_slots(
+    slots: &mut [InventorySlot],
+    resource_type: BlockType,
+    quantity: u32,
+    stack_size: u32,
+) -> bool {
+    if space_in_slots(slots, &resource_type, stack_size) < quantity {
+        return false;
+    }
+    let mut remaining = quantity;
+    let (matching, empty): (Vec<_>, Vec<_>) = slots
+        .iter_mut()
+        .filter(|slot| {
+            slot.resource_type.is_none() || slot.resource_type.as_ref() == Some(&resource_type)
+        })
+        .partition(|slot| slot.resource_type.is_some());
+    for slot in matching.into_iter().chain(empty) {
+        if remaining == 0 {
+            break;
+        }
+        let added = remaining.min(stack_size.saturating_sub(slot.quantity));
+        if added == 0 {
+            continue;
         }
+        slot.resource_type = Some(resource_type.clone());
+        slot.quantity += added;
+        remaining -= added;
+    }
+    true
+}
+
+/// Empties the slots holding blocks missing from `registry`, returning how many there were.
+pub fn forget_unknown_in_slots(slots: &mut [InventorySlot], registry: &BlockRegistry) -> usize {
+    let mut forgotten = 0;
+    for slot in slots {
+        if slot
+            .resource_type
+            .as_ref()
+            .is_some_and(|block| !registry.knows(block))
+        {
+            *slot = InventorySlot {
+                resource_type: None,
+                quantity: 0,
+            };
+            forgotten += 1;
+        }
+    }
+    forgotten
+}
+
+pub fn total_in_slots(slots: &[InventorySlot], resource_type: &BlockType) -> u32 {
+    slots
+        .iter()
+        .filter(|slot| slot.resource_type.as_ref() == Some(resource_type))
+        .map(|slot| slot.quantity)
+        .sum()
+}
+
+/// Deducts every requirement if there is enough of all of them, and returns whether it did.
+pub fn deduct_from_slots(slots: &mut [InventorySlot], requirements: &[(BlockType, u32)]) -> bool {
+    // First, check if we have enough of each resource
+    for (resource_type, required_amount) in requirements {
+        if total_in_slots(slots, resource_type) < *required_amount {
+            return false;
+        }
+    }
 
-        // If we have enough, proceed with deduction
-        for (resource_type, required_amount) in requirements {
-            let mut remaining = *required_amount;
-            for slot in &mut self.slots {
-                if slot.resource_type == Some((*resource_type).clone()) {
-                    if slot.quantity >= remaining {
-                        slot.quantity -= remaining;
-                        if slot.quantity == 0 {
-                            slot.resource_type = None;
-                        }
-                        break;
-                    } else {
-                        remaining -= slot.quantity;
-                        slot.quantity = 0;
+    // If we have enough, proceed with deduction
+    for (resource_type, required_amount) in requirements {
+        let mut remaining = *required_amount;
+        for slot in slots.iter_mut() {
+            if slot.resource_type.as_ref() == Some(resource_type) {
+                if slot.quantity >= remaining {
+                    slot.quantity -= remaining;
+                    if slot.quantity == 0 {
                         slot.resource_type = None;
                     }
+                    break;
+                } else {
+                    remaining -= slot.quantity;
+                    slot.quantity = 0;
+                    slot.resource_type = None;
                 }
             }
         }
-
-        true
     }
+
+    true
+}
+
+#[cfg(test)]
+const STONE: BlockType = BlockType::Block(super::registry::BlockId::from_raw(0));
+#[cfg(test)]
+const COAL: BlockType = BlockType::Block(super::registry::BlockId::from_raw(1));
+
+#[test]
+fn stacks_spill_into_empty_slots() {
+    let mut slots = Inventory::new(3).slots;
+    assert!(add_to_slots(&mut slots, STONE, 10, 4));
+    let quantities: Vec<_> = slots.iter().map(|slot| slot.quantity).collect();
+    assert_eq!(quantities, [4, 4, 2]);
+    // Nothing is added when not all of it fits.
+    assert!(!add_to_slots(&mut slots, STONE, 3, 4));
+    assert_eq!(total_in_slots(&slots, &STONE), 10);
+    assert!(!add_to_slots(&mut slots, COAL, 1, 4));
+}
+
+#[test]
+fn matching_slots_fill_before_empty_ones() {
+    let mut slots = Inventory::new(3).slots;
+    assert!(add_to_slots(&mut slots, COAL, 1, 64));
+    assert!(add_to_slots(&mut slots, STONE, 1, 64));
+    deduct_from_slots(&mut slots, &[(COAL, 1)]);
+    assert!(add_to_slots(&mut slots, STONE, 5, 64));
+    assert!(slots[0].resource_type.is_none());
+    assert_eq!(slots[1].quantity, 6);
 }
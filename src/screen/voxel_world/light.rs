@@ -0,0 +1,461 @@
+//! Light levels inside voxel interiors, kept separately from rendering so gameplay can query them.
+//! There are two channels, both from 0 to [`MAX_LIGHT`] and spread through transparent blocks
+//! with a breadth-first flood fill that loses one level for every block travelled:
+//! - Sky light shines down from the top of the interior without fading, then spreads sideways.
+//! - Block light comes from light-emitting blocks.
+//!
+//! Fields are updated incrementally when a single block changes, instead of being recomputed.
+
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{
+    interiors::Interiors, registry::BlockRegistry, BlockType, VoxelData, VOXEL_DIVISION_FACTOR,
+};
+use crate::screen::hex_map::cells::HexId;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<InteriorLights>();
+    app.add_systems(
+        Update,
+        update_lights
+            .in_set(LightSet)
+            .run_if(resource_exists::<BlockRegistry>.and_then(resource_changed::<Interiors>)),
+    );
+}
+
+/// Systems reading light levels of changed interiors should run after this set.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LightSet;
+
+/// The brightest a block can be lit.
+pub const MAX_LIGHT: u8 = 15;
+
+const NEIGHBOURS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+/// How blocks interact with light.
+pub trait LightRules {
+    /// Light emitted by `block`, from 0 to [`MAX_LIGHT`].
+    fn emission(&self, block: &BlockType) -> u8;
+    /// Whether light travels through `block`.
+    fn is_transparent(&self, block: &BlockType) -> bool;
+}
+
+impl LightRules for BlockRegistry {
+    fn emission(&self, block: &BlockType) -> u8 {
+        self.light(block)
+    }
+
+    fn is_transparent(&self, block: &BlockType) -> bool {
+        !self.is_opaque(block)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Channel {
+    Sky,
+    Block,
+}
+
+/// The sky and block light of every position in an interior.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LightField {
+    sky: Vec<u8>,
+    block: Vec<u8>,
+}
+
+impl LightField {
+    /// Sky light at `pos`. Positions outside of the grid are dark.
+    pub fn sky(&self, pos: IVec3) -> u8 {
+        VoxelData::index(pos).map_or(0, |index| self.sky[index])
+    }
+
+    /// Block light at `pos`. Positions outside of the grid are dark.
+    pub fn block(&self, pos: IVec3) -> u8 {
+        VoxelData::index(pos).map_or(0, |index| self.block[index])
+    }
+
+    /// The brightest of both channels at `pos`.
+    pub fn get(&self, pos: IVec3) -> u8 {
+        self.sky(pos).max(self.block(pos))
+    }
+
+    /// Lights `interior` from scratch.
+    pub fn compute(interior: &VoxelData, rules: &impl LightRules) -> Self {
+        let len = VOXEL_DIVISION_FACTOR.pow(3);
+        let mut field = Self {
+            sky: vec![0; len],
+            block: vec![0; len],
+        };
+        let mut sky = VecDeque::new();
+        let mut block = VecDeque::new();
+        for (pos, _) in interior.iter() {
+            if field.seed(Channel::Sky, interior, rules, pos) {
+                sky.push_back(pos);
+            }
+            if field.seed(Channel::Block, interior, rules, pos) {
+                block.push_back(pos);
+            }
+        }
+        field.spread(Channel::Sky, interior, rules, sky);
+        field.spread(Channel::Block, interior, rules, block);
+        field
+    }
+
+    /// Relights the area around `pos` after the block there changed.
+    /// `interior` must already contain the new block.
+    pub fn update(&mut self, interior: &VoxelData, rules: &impl LightRules, pos: IVec3) {
+        if VoxelData::index(pos).is_none() {
+            return;
+        }
+        for channel in [Channel::Sky, Channel::Block] {
+            let mut relight = self.darken(channel, interior, rules, pos);
+            if self.seed(channel, interior, rules, pos) {
+                relight.push_back(pos);
+            }
+            // Neighbours may now shine into `pos`.
+            relight.extend(
+                NEIGHBOURS
+                    .iter()
+                    .map(|offset| pos + *offset)
+                    .filter(|next| self.level(channel, *next) > 0),
+            );
+            self.spread(channel, interior, rules, relight);
+        }
+    }
+
+    fn levels(&mut self, channel: Channel) -> &mut [u8] {
+        match channel {
+            Channel::Sky => &mut self.sky,
+            Channel::Block => &mut self.block,
+        }
+    }
+
+    fn level(&self, channel: Channel, pos: IVec3) -> u8 {
+        match channel {
+            Channel::Sky => self.sky(pos),
+            Channel::Block => self.block(pos),
+        }
+    }
+
+    /// Sets the light that `pos` produces by itself, returning whether there is any.
+    /// Sky light comes in through the top of the interior and block light from emitters.
+    fn seed(
+        &mut self,
+        channel: Channel,
+        interior: &VoxelData,
+        rules: &impl LightRules,
+        pos: IVec3,
+    ) -> bool {
+        let (Some(index), Some(block)) = (VoxelData::index(pos), interior.get(pos)) else {
+            return false;
+        };
+        let level = match channel {
+            Channel::Sky => {
+                let top = VOXEL_DIVISION_FACTOR as i32 - 1;
+                if pos.y == top && rules.is_transparent(block) {
+                    MAX_LIGHT
+                } else {
+                    0
+                }
+            }
+            Channel::Block => rules.emission(block).min(MAX_LIGHT),
+        };
+        let levels = self.levels(channel);
+        if level > levels[index] {
+            levels[index] = level;
+        }
+        level > 0
+    }
+
+    /// Light `channel` gives to the neighbour in direction `offset` of a block lit at `level`.
+    fn falloff(channel: Channel, level: u8, offset: IVec3) -> u8 {
+        if channel == Channel::Sky && offset == IVec3::NEG_Y && level == MAX_LIGHT {
+            MAX_LIGHT
+        } else {
+            level.saturating_sub(1)
+        }
+    }
+
+    /// Flood fills light from every position in `queue` into transparent neighbours.
+    fn spread(
+        &mut self,
+        channel: Channel,
+        interior: &VoxelData,
+        rules: &impl LightRules,
+        mut queue: VecDeque<IVec3>,
+    ) {
+        while let Some(pos) = queue.pop_front() {
+            let level = self.level(channel, pos);
+            for offset in NEIGHBOURS {
+                let next = pos + offset;
+                let (Some(index), Some(block)) = (VoxelData::index(next), interior.get(next))
+                else {
+                    continue;
+                };
+                let light = Self::falloff(channel, level, offset);
+                let levels = self.levels(channel);
+                if rules.is_transparent(block) && levels[index] < light {
+                    levels[index] = light;
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    /// Removes the light at `pos` and everything that was lit through it.
+    /// Returns the positions lit by other sources at the edge of the removed area,
+    /// which have to spread their light back in.
+    fn darken(
+        &mut self,
+        channel: Channel,
+        interior: &VoxelData,
+        rules: &impl LightRules,
+        pos: IVec3,
+    ) -> VecDeque<IVec3> {
+        let mut relight = VecDeque::new();
+        let index = VoxelData::index(pos).unwrap();
+        let levels = self.levels(channel);
+        let mut queue = VecDeque::from([(pos, levels[index])]);
+        levels[index] = 0;
+        while let Some((pos, level)) = queue.pop_front() {
+            for offset in NEIGHBOURS {
+                let next = pos + offset;
+                let Some(index) = VoxelData::index(next) else {
+                    continue;
+                };
+                let levels = self.levels(channel);
+                let light = levels[index];
+                if light == 0 {
+                    continue;
+                }
+                if light < level || light == Self::falloff(channel, level, offset) {
+                    levels[index] = 0;
+                    queue.push_back((next, light));
+                    // Sources in the removed area shine again on their own.
+                    if self.seed(channel, interior, rules, next) {
+                        relight.push_back(next);
+                    }
+                } else {
+                    relight.push_back(next);
+                }
+            }
+        }
+        relight
+    }
+}
+
+/// The light field of every interior, kept up to date with [`Interiors`].
+#[derive(Resource, Default)]
+pub struct InteriorLights(HashMap<HexId, LitInterior>);
+
+/// A light field along with the blocks it was computed for.
+struct LitInterior {
+    interior: VoxelData,
+    light: LightField,
+}
+
+impl InteriorLights {
+    pub fn get(&self, id: HexId) -> Option<&LightField> {
+        self.0.get(&id).map(|lit| &lit.light)
+    }
+
+    /// Brings the light field of `id` up to date with `interior` and returns it.
+    /// Only the blocks that changed since the last call are relit.
+    pub fn sync(
+        &mut self,
+        id: HexId,
+        interior: &VoxelData,
+        rules: &impl LightRules,
+    ) -> &LightField {
+        let lit = self.0.entry(id).or_insert_with(|| LitInterior {
+            interior: interior.clone(),
+            light: LightField::compute(interior, rules),
+        });
+        if !lit.interior.shares_blocks(interior) {
+            let changed: Vec<_> = lit
+                .interior
+                .iter()
+                .zip(interior.iter())
+                .filter(|((_, old), (_, new))| old != new)
+                .map(|((pos, _), _)| pos)
+                .collect();
+            for pos in changed {
+                lit.light.update(interior, rules, pos);
+            }
+            lit.interior = interior.clone();
+        }
+        &lit.light
+    }
+}
+
+fn update_lights(
+    interiors: Res<Interiors>,
+    mut lights: ResMut<InteriorLights>,
+    registry: Res<BlockRegistry>,
+) {
+    // Loading a save replaces every interior.
+    lights.0.retain(|id, _| interiors.get(*id).is_some());
+    for (id, interior) in interiors.iter() {
+        lights.sync(id, interior, &*registry);
+    }
+}
+
+/// Stone is opaque and lamps shine at full brightness.
+#[cfg(test)]
+struct TestRules;
+
+#[cfg(test)]
+const STONE: BlockType = BlockType::Block(super::registry::BlockId::from_raw(0));
+#[cfg(test)]
+const LAMP: BlockType = BlockType::Block(super::registry::BlockId::from_raw(1));
+
+#[cfg(test)]
+impl LightRules for TestRules {
+    fn emission(&self, block: &BlockType) -> u8 {
+        if *block == LAMP {
+            MAX_LIGHT
+        } else {
+            0
+        }
+    }
+
+    fn is_transparent(&self, block: &BlockType) -> bool {
+        *block == BlockType::Air
+    }
+}
+
+/// An interior closed off from the sky by a stone ceiling.
+#[cfg(test)]
+fn covered() -> VoxelData {
+    let mut interior = VoxelData::default();
+    let top = VOXEL_DIVISION_FACTOR as i32 - 1;
+    for x in 0..VOXEL_DIVISION_FACTOR as i32 {
+        for z in 0..VOXEL_DIVISION_FACTOR as i32 {
+            interior.set(IVec3::new(x, top, z), STONE);
+        }
+    }
+    interior
+}
+
+#[test]
+fn sky_light_falls_without_fading() {
+    let mut interior = VoxelData::default();
+    interior.set(IVec3::new(4, 10, 4), STONE);
+    let light = LightField::compute(&interior, &TestRules);
+    assert_eq!(light.sky(IVec3::new(0, 0, 0)), MAX_LIGHT);
+    assert_eq!(light.sky(IVec3::new(4, 11, 4)), MAX_LIGHT);
+    assert_eq!(light.sky(IVec3::new(4, 10, 4)), 0);
+    // Under the stone, light only comes in from the sides.
+    assert_eq!(light.sky(IVec3::new(4, 9, 4)), MAX_LIGHT - 1);
+    assert_eq!(light.block(IVec3::new(0, 0, 0)), 0);
+}
+
+#[test]
+fn covered_interiors_are_dark() {
+    let light = LightField::compute(&covered(), &TestRules);
+    assert!(VoxelData::default()
+        .iter()
+        .all(|(pos, _)| light.get(pos) == 0));
+}
+
+#[test]
+fn block_light_fades_with_distance() {
+    let mut interior = covered();
+    let lamp = IVec3::new(2, 2, 2);
+    interior.set(lamp, LAMP);
+    let light = LightField::compute(&interior, &TestRules);
+    assert_eq!(light.block(lamp), MAX_LIGHT);
+    assert_eq!(light.block(lamp + IVec3::X), MAX_LIGHT - 1);
+    assert_eq!(light.block(lamp + IVec3::new(3, 2, 1)), MAX_LIGHT - 6);
+    assert_eq!(light.block(IVec3::new(15, 14, 15)), 0);
+    assert_eq!(light.sky(lamp), 0);
+}
+
+#[test]
+fn walls_block_light() {
+    let mut interior = covered();
+    // A hollow box of stone around a lamp.
+    for x in 0..5 {
+        for y in 0..5 {
+            for z in 0..5 {
+                let pos = IVec3::new(x, y, z);
+                let inside = pos.cmpgt(IVec3::ZERO).all() && pos.cmplt(IVec3::splat(4)).all();
+                interior.set(pos, if inside { BlockType::Air } else { STONE });
+            }
+        }
+    }
+    interior.set(IVec3::new(2, 2, 2), LAMP);
+    let light = LightField::compute(&interior, &TestRules);
+    assert_eq!(light.block(IVec3::new(3, 2, 2)), MAX_LIGHT - 1);
+    assert_eq!(light.block(IVec3::new(4, 2, 2)), 0);
+    assert_eq!(light.block(IVec3::new(5, 2, 2)), 0);
+}
+
+/// Applies a series of block changes and checks the incremental result against a full relight.
+#[cfg(test)]
+fn assert_updates_match(mut interior: VoxelData, changes: &[(IVec3, BlockType)]) {
+    let mut light = LightField::compute(&interior, &TestRules);
+    for (pos, block) in changes {
+        interior.set(*pos, block.clone());
+        light.update(&interior, &TestRules, *pos);
+        assert_eq!(
+            light,
+            LightField::compute(&interior, &TestRules),
+            "after {pos}"
+        );
+    }
+}
+
+#[test]
+fn placing_and_removing_lamps() {
+    assert_updates_match(
+        covered(),
+        &[
+            (IVec3::new(3, 3, 3), LAMP),
+            (IVec3::new(6, 3, 3), LAMP),
+            (IVec3::new(3, 3, 3), BlockType::Air),
+            (IVec3::new(5, 3, 3), STONE),
+            (IVec3::new(6, 3, 3), BlockType::Air),
+            (IVec3::new(5, 3, 3), BlockType::Air),
+        ],
+    );
+}
+
+#[test]
+fn opening_and_closing_the_sky() {
+    let top = VOXEL_DIVISION_FACTOR as i32 - 1;
+    assert_updates_match(
+        covered(),
+        &[
+            (IVec3::new(7, top, 7), BlockType::Air),
+            (IVec3::new(7, 4, 7), STONE),
+            (IVec3::new(8, top, 7), BlockType::Air),
+            (IVec3::new(7, top, 7), STONE),
+            (IVec3::new(7, 4, 7), LAMP),
+            (IVec3::new(8, top, 7), STONE),
+        ],
+    );
+}
+
+#[test]
+fn lamps_next_to_each_other() {
+    assert_updates_match(
+        covered(),
+        &[
+            (IVec3::new(3, 3, 3), LAMP),
+            (IVec3::new(4, 3, 3), LAMP),
+            (IVec3::new(3, 3, 3), BlockType::Air),
+            (IVec3::new(3, 3, 3), STONE),
+            (IVec3::new(4, 3, 3), STONE),
+        ],
+    );
+}
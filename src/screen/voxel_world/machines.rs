@@ -0,0 +1,686 @@
+//! Machines that work on their own inside interiors, turning cells into factories.
+//! Every machine faces a horizontal direction and stores items in inventory slots.
+//! Each time it works, it does its job and then passes one item on to the machine it outputs to.
+//! Machines only exist as data next to the interiors, so the simulation keeps them working in
+//! every cell even while the player is somewhere else. Machines on the face of an interior output
+//! to, and mine from, the matching face of the neighbouring cell, so items can travel across the
+//! hex map.
+
+use std::time::Duration;
+
+use bevy::{prelude::*, ui::Val::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    interaction::target,
+    interiors::{resolve, Interiors},
+    inventory::{
+        add_to_slots, deduct_from_slots, forget_unknown_in_slots, space_in_slots, Inventory,
+        InventorySlot,
+    },
+    registry::{BlockRegistry, MachineDefinition, MachineKind},
+    voxel_util::VoxelPlayer,
+    BlockType,
+};
+use crate::{
+    game::{
+        input::{action_just_pressed, Action},
+        mode::GameMode,
+    },
+    screen::{hex_map::cells::HexId, HexSelect, Screen},
+    ui::palette::LABEL_TEXT,
+    AppSet,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<Machines>();
+    app.add_systems(OnEnter(Screen::VoxelWorld), spawn_machine_hud);
+    app.add_systems(
+        Update,
+        (
+            take_items.run_if(action_just_pressed(Action::TakeItems)),
+            update_machine_hud,
+        )
+            .chain()
+            .in_set(AppSet::Update)
+            .run_if(in_state(Screen::VoxelWorld).and_then(resource_exists::<BlockRegistry>)),
+    );
+}
+
+/// The state of a placed machine block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Machine {
+    /// Direction the machine faces, along one axis.
+    facing: IVec3,
+    timer: Timer,
+    slots: Vec<InventorySlot>,
+}
+
+impl Machine {
+    pub fn new(definition: &MachineDefinition, facing: IVec3) -> Self {
+        Machine {
+            facing,
+            timer: Timer::from_seconds(definition.interval, TimerMode::Repeating),
+            slots: vec![
+                InventorySlot {
+                    resource_type: None,
+                    quantity: 0,
+                };
+                definition.slots
+            ],
+        }
+    }
+
+    /// Every item stored in the machine.
+    pub fn items(&self) -> impl Iterator<Item = (&BlockType, u32)> {
+        self.slots
+            .iter()
+            .filter_map(|slot| Some((slot.resource_type.as_ref()?, slot.quantity)))
+    }
+}
+
+/// The machines of every interior, by cell and position.
+#[derive(Resource, Default, Clone, Serialize, Deserialize)]
+pub struct Machines(HashMap<HexId, HashMap<IVec3, Machine>>);
+
+impl Machines {
+    pub fn get(&self, id: HexId, pos: IVec3) -> Option<&Machine> {
+        self.0.get(&id)?.get(&pos)
+    }
+
+    fn get_mut(&mut self, id: HexId, pos: IVec3) -> Option<&mut Machine> {
+        self.0.get_mut(&id)?.get_mut(&pos)
+    }
+
+    pub fn insert(&mut self, id: HexId, pos: IVec3, machine: Machine) {
+        self.0.entry(id).or_default().insert(pos, machine);
+    }
+
+    /// Empties the slots holding blocks missing from `registry`, returning how many there were.
+    /// Machines whose own block is missing are pruned once their interior runs.
+    pub fn forget_unknown_blocks(&mut self, registry: &BlockRegistry) -> usize {
+        self.0
+            .values_mut()
+            .flat_map(HashMap::values_mut)
+            .map(|machine| forget_unknown_in_slots(&mut machine.slots, registry))
+            .sum()
+    }
+
+    /// Removes the machine at `pos`, returning it so its items aren't lost.
+    pub fn remove(&mut self, id: HexId, pos: IVec3) -> Option<Machine> {
+        self.0.get_mut(&id)?.remove(&pos)
+    }
+}
+
+/// The direction closest to `forward`, which machines placed looking that way face.
+/// Only machines that can face up and down take the vertical axis into account.
+pub fn facing(definition: &MachineDefinition, forward: Vec3) -> IVec3 {
+    let forward = if definition.vertical {
+        forward
+    } else {
+        forward.with_y(0.)
+    };
+    let abs = forward.abs();
+    let axis = if abs.x >= abs.y && abs.x >= abs.z {
+        IVec3::X
+    } else if abs.y >= abs.z {
+        IVec3::Y
+    } else {
+        IVec3::Z
+    };
+    axis * forward.dot(axis.as_vec3()).signum() as i32
+}
+
+/// Runs the machines of the interior of `id` for `delta` of the [simulation](super::simulation).
+pub(super) fn run(
+    machines: &mut Machines,
+    interiors: &mut Interiors,
+    registry: &BlockRegistry,
+    id: HexId,
+    delta: Duration,
+) {
+    let Some(cell) = machines.0.get_mut(&id) else {
+        return;
+    };
+    // Machines whose block is gone are gone too.
+    let interior = interiors.get(id);
+    cell.retain(|pos, _| {
+        interior
+            .and_then(|interior| interior.get(*pos))
+            .is_some_and(|block| registry.machine(block).is_some())
+    });
+    let working: Vec<_> = cell
+        .iter_mut()
+        .filter_map(|(pos, machine)| {
+            machine.timer.tick(delta);
+            machine.timer.just_finished().then_some(*pos)
+        })
+        .collect();
+
+    for pos in working {
+        let Some(kind) = interiors
+            .block_at(id, pos)
+            .and_then(|block| registry.machine(block))
+            .map(|definition| definition.kind.clone())
+        else {
+            continue;
+        };
+        work(machines, interiors, registry, id, pos, &kind);
+        pass_on(machines, interiors, registry, id, pos, &kind);
+    }
+}
+
+/// Does the job of the machine at `pos`.
+fn work(
+    machines: &mut Machines,
+    interiors: &mut Interiors,
+    registry: &BlockRegistry,
+    id: HexId,
+    pos: IVec3,
+    kind: &MachineKind,
+) {
+    let Some(machine) = machines.get_mut(id, pos) else {
+        return;
+    };
+    match kind {
+        MachineKind::Miner => {
+            let Some((front_id, front)) = resolve(id, pos + machine.facing) else {
+                return;
+            };
+            let Some(block) = interiors.block_at(front_id, front) else {
+                return;
+            };
+            // Miners don't take apart other machines.
+            let BlockType::Block(target) = block else {
+                return;
+            };
+            if registry.machine(block).is_some() {
+                return;
+            }
+            let drop = BlockType::Block(registry.drop(*target));
+            let stack_size = registry.stack_size(&drop);
+            if add_to_slots(&mut machine.slots, drop, 1, stack_size) {
+                if let Some(interior) = interiors.get_mut(front_id) {
+                    interior.set(front, BlockType::Air);
+                }
+            }
+        }
+        MachineKind::Furnace(recipes) => {
+            for (inputs, (output, amount)) in
+                recipes.iter().filter_map(|recipe| registry.recipe(recipe))
+            {
+                // Only smelt when the output fits.
+                let mut slots = machine.slots.clone();
+                if deduct_from_slots(&mut slots, &inputs)
+                    && add_to_slots(
+                        &mut slots,
+                        output.clone(),
+                        amount,
+                        registry.stack_size(&output),
+                    )
+                {
+                    machine.slots = slots;
+                    return;
+                }
+            }
+        }
+        MachineKind::Conveyor | MachineKind::Container => {}
+    }
+}
+
+/// Moves one item from the machine at `pos` to the machine it outputs to, if that one takes it.
+fn pass_on(
+    machines: &mut Machines,
+    interiors: &Interiors,
+    registry: &BlockRegistry,
+    id: HexId,
+    pos: IVec3,
+    kind: &MachineKind,
+) {
+    let Some(machine) = machines.get(id, pos) else {
+        return;
+    };
+    let target = match kind {
+        // Miners face the block they mine.
+        MachineKind::Miner => pos - machine.facing,
+        MachineKind::Conveyor | MachineKind::Furnace(_) => pos + machine.facing,
+        MachineKind::Container => return,
+    };
+    let Some((target_id, target)) = resolve(id, target) else {
+        return;
+    };
+    let Some(target_kind) = interiors
+        .block_at(target_id, target)
+        .and_then(|block| registry.machine(block))
+        .map(|definition| &definition.kind)
+    else {
+        return;
+    };
+    let Some(item) = machine
+        .items()
+        .map(|(item, _)| item)
+        .find(|item| is_output(kind, item, registry) && accepts(target_kind, item, registry))
+        .cloned()
+    else {
+        return;
+    };
+    let Some(receiver) = machines.get_mut(target_id, target) else {
+        return;
+    };
+    let stack_size = registry.stack_size(&item);
+    if !add_to_slots(&mut receiver.slots, item.clone(), 1, stack_size) {
+        return;
+    }
+    if let Some(machine) = machines.get_mut(id, pos) {
+        deduct_from_slots(&mut machine.slots, &[(item, 1)]);
+    }
+}
+
+/// Whether a machine of `kind` passes `item` on. Furnaces keep their inputs.
+fn is_output(kind: &MachineKind, item: &BlockType, registry: &BlockRegistry) -> bool {
+    match kind {
+        MachineKind::Furnace(recipes) => recipes
+            .iter()
+            .filter_map(|recipe| registry.recipe(recipe))
+            .any(|(_, (output, _))| output == *item),
+        _ => true,
+    }
+}
+
+/// Whether a machine of `kind` takes `item` from other machines.
+fn accepts(kind: &MachineKind, item: &BlockType, registry: &BlockRegistry) -> bool {
+    match kind {
+        MachineKind::Miner => false,
+        MachineKind::Furnace(recipes) => recipes
+            .iter()
+            .filter_map(|recipe| registry.recipe(recipe))
+            .any(|(inputs, _)| inputs.iter().any(|(input, _)| input == item)),
+        MachineKind::Conveyor | MachineKind::Container => true,
+    }
+}
+
+/// Moves everything stored in the machine the player is looking at into their inventory.
+fn take_items(
+    camera: Query<&GlobalTransform, With<VoxelPlayer>>,
+    hex_select: Res<HexSelect>,
+    interiors: Res<Interiors>,
+    mut machines: ResMut<Machines>,
+    mut inventory: ResMut<Inventory>,
+    registry: Res<BlockRegistry>,
+    mode: Res<GameMode>,
+) {
+    // The creative inventory already holds everything.
+    if *mode == GameMode::Creative {
+        return;
+    }
+    let Some(hit) = target(&camera, &interiors, &hex_select) else {
+        return;
+    };
+    let Some(machine) = machines
+        .0
+        .get_mut(&hex_select.hex_id)
+        .and_then(|cell| cell.get_mut(&hit.pos))
+    else {
+        return;
+    };
+    // Whatever doesn't fit in the inventory stays in the machine.
+    for slot in &mut machine.slots {
+        let Some(item) = slot.resource_type.clone() else {
+            continue;
+        };
+        let space = space_in_slots(&inventory.slots, &item, registry.stack_size(&item));
+        let taken = slot.quantity.min(space);
+        if taken == 0 || !inventory.add_resource(item, taken, &registry) {
+            continue;
+        }
+        slot.quantity -= taken;
+        if slot.quantity == 0 {
+            slot.resource_type = None;
+        }
+    }
+}
+
+/// Text telling what is in the machine the player is looking at.
+#[derive(Component)]
+struct MachineHud;
+
+fn spawn_machine_hud(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Machine HUD"),
+        MachineHud,
+        StateScoped(Screen::VoxelWorld),
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.0,
+                color: LABEL_TEXT,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Px(10.0),
+            right: Px(10.0),
+            ..default()
+        }),
+    ));
+}
+
+fn update_machine_hud(
+    camera: Query<&GlobalTransform, With<VoxelPlayer>>,
+    hex_select: Res<HexSelect>,
+    interiors: Res<Interiors>,
+    machines: Res<Machines>,
+    registry: Res<BlockRegistry>,
+    mut texts: Query<&mut Text, With<MachineHud>>,
+) {
+    let description = target(&camera, &interiors, &hex_select)
+        .and_then(|hit| {
+            let machine = machines.get(hex_select.hex_id, hit.pos)?;
+            let block = interiors.get(hex_select.hex_id)?.get(hit.pos)?;
+            Some((machine, block))
+        })
+        .map(|(machine, block)| {
+            let items: Vec<_> = machine
+                .items()
+                .map(|(item, quantity)| format!("{quantity} {}", registry.name(item)))
+                .collect();
+            let items = if items.is_empty() {
+                "empty".to_string()
+            } else {
+                items.join(", ")
+            };
+            format!("{}: {items}", registry.name(block))
+        })
+        .unwrap_or_default();
+    for mut text in &mut texts {
+        if text.sections[0].value != description {
+            text.sections[0].value.clone_from(&description);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen::{voxel_world::VoxelData, MapDirection};
+
+    fn test_registry() -> BlockRegistry {
+        BlockRegistry::from_ron(
+            r#"(blocks: [
+                (name: "stone", stack_size: 2),
+                (name: "coal", stack_size: 4),
+                (name: "brick"),
+                (name: "miner", machine: (kind: Miner, slots: 1)),
+                (name: "conveyor", machine: (kind: Conveyor, slots: 1)),
+                (name: "furnace", machine: (
+                    kind: Furnace([(inputs: [("coal", 1), ("stone", 2)], output: ("brick", 2))]),
+                )),
+                (name: "crate", machine: (kind: Container, slots: 2)),
+            ])"#,
+        )
+    }
+
+    /// The cell machines are tested in, with an empty interior.
+    const CELL: HexId = HexId::new(0, 0);
+
+    /// Every machine in the test registry works once a second.
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn empty_cell() -> (Interiors, Machines) {
+        let mut interiors = Interiors::default();
+        interiors.insert(CELL, VoxelData::default());
+        (interiors, Machines::default())
+    }
+
+    /// Puts the block `name` at `pos` of `id` along with its machine, replacing what was there.
+    fn place(
+        interiors: &mut Interiors,
+        machines: &mut Machines,
+        registry: &BlockRegistry,
+        id: HexId,
+        pos: IVec3,
+        name: &str,
+    ) {
+        let block = registry.block(name).unwrap();
+        let definition = registry.machine(&block).unwrap();
+        machines.insert(id, pos, Machine::new(definition, IVec3::X));
+        interiors.get_mut(id).unwrap().set(pos, block);
+    }
+
+    fn fill(
+        machines: &mut Machines,
+        registry: &BlockRegistry,
+        id: HexId,
+        pos: IVec3,
+        items: &[(&str, u32)],
+    ) {
+        let machine = machines.get_mut(id, pos).unwrap();
+        for (name, quantity) in items {
+            let item = registry.block(name).unwrap();
+            let stack_size = registry.stack_size(&item);
+            assert!(add_to_slots(
+                &mut machine.slots,
+                item,
+                *quantity,
+                stack_size
+            ));
+        }
+    }
+
+    fn contents(
+        machines: &Machines,
+        registry: &BlockRegistry,
+        id: HexId,
+        pos: IVec3,
+    ) -> Vec<(String, u32)> {
+        machines
+            .get(id, pos)
+            .unwrap()
+            .items()
+            .map(|(item, quantity)| (registry.name(item).to_string(), quantity))
+            .collect()
+    }
+
+    #[test]
+    fn furnaces_smelt_once_they_have_every_input() {
+        let registry = test_registry();
+        let (mut interiors, mut machines) = empty_cell();
+        let furnace = IVec3::new(5, 1, 5);
+        place(
+            &mut interiors,
+            &mut machines,
+            &registry,
+            CELL,
+            furnace,
+            "furnace",
+        );
+        fill(
+            &mut machines,
+            &registry,
+            CELL,
+            furnace,
+            &[("coal", 1), ("stone", 1)],
+        );
+
+        run(&mut machines, &mut interiors, &registry, CELL, SECOND);
+        assert_eq!(
+            contents(&machines, &registry, CELL, furnace),
+            [("coal".to_string(), 1), ("stone".to_string(), 1)]
+        );
+
+        fill(&mut machines, &registry, CELL, furnace, &[("stone", 1)]);
+        run(&mut machines, &mut interiors, &registry, CELL, SECOND);
+        assert_eq!(
+            contents(&machines, &registry, CELL, furnace),
+            [("brick".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn conveyors_only_pass_into_machines_with_room() {
+        let registry = test_registry();
+        let (mut interiors, mut machines) = empty_cell();
+        let conveyor = IVec3::new(5, 1, 5);
+        let target = conveyor + IVec3::X;
+        place(
+            &mut interiors,
+            &mut machines,
+            &registry,
+            CELL,
+            conveyor,
+            "conveyor",
+        );
+        fill(&mut machines, &registry, CELL, conveyor, &[("stone", 1)]);
+
+        // There is no machine to pass into.
+        run(&mut machines, &mut interiors, &registry, CELL, SECOND);
+        assert_eq!(
+            contents(&machines, &registry, CELL, conveyor),
+            [("stone".to_string(), 1)]
+        );
+
+        // The crate is full.
+        place(
+            &mut interiors,
+            &mut machines,
+            &registry,
+            CELL,
+            target,
+            "crate",
+        );
+        fill(&mut machines, &registry, CELL, target, &[("coal", 8)]);
+        run(&mut machines, &mut interiors, &registry, CELL, SECOND);
+        assert_eq!(
+            contents(&machines, &registry, CELL, conveyor),
+            [("stone".to_string(), 1)]
+        );
+
+        place(
+            &mut interiors,
+            &mut machines,
+            &registry,
+            CELL,
+            target,
+            "crate",
+        );
+        run(&mut machines, &mut interiors, &registry, CELL, SECOND);
+        assert!(contents(&machines, &registry, CELL, conveyor).is_empty());
+        assert_eq!(
+            contents(&machines, &registry, CELL, target),
+            [("stone".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn full_miners_stop_mining() {
+        let registry = test_registry();
+        let (mut interiors, mut machines) = empty_cell();
+        let miner = IVec3::new(5, 1, 5);
+        let front = miner + IVec3::X;
+        let stone = registry.block("stone").unwrap();
+        place(
+            &mut interiors,
+            &mut machines,
+            &registry,
+            CELL,
+            miner,
+            "miner",
+        );
+
+        // The only slot of the miner holds two stone.
+        for mined in [1, 2, 2] {
+            interiors.get_mut(CELL).unwrap().set(front, stone.clone());
+            run(&mut machines, &mut interiors, &registry, CELL, SECOND);
+            assert_eq!(
+                contents(&machines, &registry, CELL, miner),
+                [("stone".to_string(), mined)]
+            );
+        }
+        assert_eq!(interiors.block_at(CELL, front), Some(&stone));
+    }
+
+    #[test]
+    fn machines_reach_across_faces() {
+        let registry = test_registry();
+        let (mut interiors, mut machines) = empty_cell();
+        let neighbour = CELL + MapDirection::from_face_normal(IVec3::X).unwrap();
+        interiors.insert(neighbour, VoxelData::default());
+        let edge = IVec3::new(15, 1, 8);
+        let across = IVec3::new(0, 1, 8);
+
+        place(
+            &mut interiors,
+            &mut machines,
+            &registry,
+            CELL,
+            edge,
+            "conveyor",
+        );
+        place(
+            &mut interiors,
+            &mut machines,
+            &registry,
+            neighbour,
+            across,
+            "crate",
+        );
+        fill(&mut machines, &registry, CELL, edge, &[("coal", 1)]);
+        run(&mut machines, &mut interiors, &registry, CELL, SECOND);
+        assert_eq!(
+            contents(&machines, &registry, neighbour, across),
+            [("coal".to_string(), 1)]
+        );
+
+        machines.remove(neighbour, across);
+        let coal = registry.block("coal").unwrap();
+        interiors.get_mut(neighbour).unwrap().set(across, coal);
+        place(
+            &mut interiors,
+            &mut machines,
+            &registry,
+            CELL,
+            edge,
+            "miner",
+        );
+        run(&mut machines, &mut interiors, &registry, CELL, SECOND);
+        assert_eq!(interiors.block_at(neighbour, across), Some(&BlockType::Air));
+        assert_eq!(
+            contents(&machines, &registry, CELL, edge),
+            [("coal".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn machines_wait_for_ungenerated_neighbours() {
+        let registry = test_registry();
+        let (mut interiors, mut machines) = empty_cell();
+        let edge = IVec3::new(15, 1, 8);
+
+        place(
+            &mut interiors,
+            &mut machines,
+            &registry,
+            CELL,
+            edge,
+            "conveyor",
+        );
+        fill(&mut machines, &registry, CELL, edge, &[("coal", 1)]);
+        run(&mut machines, &mut interiors, &registry, CELL, SECOND);
+        assert_eq!(
+            contents(&machines, &registry, CELL, edge),
+            [("coal".to_string(), 1)]
+        );
+
+        place(
+            &mut interiors,
+            &mut machines,
+            &registry,
+            CELL,
+            edge,
+            "miner",
+        );
+        run(&mut machines, &mut interiors, &registry, CELL, SECOND);
+        assert!(contents(&machines, &registry, CELL, edge).is_empty());
+    }
+}
@@ -0,0 +1,282 @@
+//! Meshing of voxel interiors.
+//! The whole interior is rendered as a single mesh using the shared block atlas,
+//! with faces between two blocks culled. Air never produces any geometry.
+//! Ambient occlusion and block light are baked into the vertex colors.
+
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_asset::RenderAssetUsages, render_resource::PrimitiveTopology},
+};
+use bevy_rapier3d::prelude::Collider;
+
+use super::{
+    fluid::{Fluid, MAX_FLUID_LEVEL},
+    interiors::Interiors,
+    light::{InteriorLights, LightField, LightSet, MAX_LIGHT},
+    registry::BlockRegistry,
+    voxel_util::Blocks,
+    BlockType, VoxelData,
+};
+use crate::screen::{HexSelect, Screen};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        update_interior_mesh.after(LightSet).run_if(
+            in_state(Screen::VoxelWorld)
+                .and_then(resource_exists::<Blocks>)
+                .and_then(resource_changed::<Interiors>),
+        ),
+    );
+}
+
+/// Marker for the entity rendering the interior the player is in, holding the interior it was
+/// built from.
+#[derive(Component)]
+pub struct InteriorMesh(VoxelData);
+
+/// A face of a block, described by the direction it faces and the directions
+/// of the right and up edges of its texture. `right.cross(up)` is the normal.
+struct Face {
+    normal: IVec3,
+    right: Vec3,
+    up: Vec3,
+}
+
+const FACES: [Face; 6] = [
+    Face {
+        normal: IVec3::X,
+        right: Vec3::NEG_Z,
+        up: Vec3::Y,
+    },
+    Face {
+        normal: IVec3::NEG_X,
+        right: Vec3::Z,
+        up: Vec3::Y,
+    },
+    Face {
+        normal: IVec3::Y,
+        right: Vec3::X,
+        up: Vec3::NEG_Z,
+    },
+    Face {
+        normal: IVec3::NEG_Y,
+        right: Vec3::X,
+        up: Vec3::Z,
+    },
+    Face {
+        normal: IVec3::Z,
+        right: Vec3::X,
+        up: Vec3::Y,
+    },
+    Face {
+        normal: IVec3::NEG_Z,
+        right: Vec3::NEG_X,
+        up: Vec3::Y,
+    },
+];
+
+/// Whether `block` completely hides the faces of its neighbours.
+fn is_opaque(block: Option<&BlockType>, registry: &BlockRegistry) -> bool {
+    block.is_some_and(|block| registry.is_opaque(block))
+}
+
+/// Whether the face between `block` and `neighbour` is inside a body of fluid.
+fn is_inside_fluid(block: &BlockType, neighbour: Option<&BlockType>) -> bool {
+    matches!((block, neighbour), (BlockType::Fluid(a), Some(BlockType::Fluid(b))) if a.id == b.id)
+}
+
+/// Height of the surface of the fluid at `pos`, from the bottom of the block.
+/// Fluid with more of it above fills the whole block.
+fn fluid_height(interior: &VoxelData, pos: IVec3, fluid: &Fluid) -> f32 {
+    if is_inside_fluid(&BlockType::Fluid(*fluid), interior.get(pos + IVec3::Y)) {
+        1.
+    } else {
+        fluid.level as f32 / (MAX_FLUID_LEVEL + 1) as f32
+    }
+}
+
+/// Brightness of a vertex for each number of occluding neighbours, from none to fully enclosed.
+const AO_BRIGHTNESS: [f32; 4] = [1., 0.8, 0.6, 0.4];
+
+/// Brightness of faces without any block light, so lit areas stand out.
+const UNLIT_BRIGHTNESS: f32 = 0.5;
+
+/// Ambient occlusion of a vertex, as the number of opaque blocks around it from 0 to 3.
+/// `side` and `up` point from the center of the face towards the vertex, and `front` is the
+/// position in front of the face.
+fn occlusion(
+    interior: &VoxelData,
+    registry: &BlockRegistry,
+    front: IVec3,
+    side: IVec3,
+    up: IVec3,
+) -> usize {
+    let side_opaque = is_opaque(interior.get(front + side), registry);
+    let up_opaque = is_opaque(interior.get(front + up), registry);
+    if side_opaque && up_opaque {
+        // The corner is hidden either way.
+        return 3;
+    }
+    let corner_opaque = is_opaque(interior.get(front + side + up), registry);
+    [side_opaque, up_opaque, corner_opaque]
+        .into_iter()
+        .filter(|opaque| *opaque)
+        .count()
+}
+
+/// Builds the render mesh of an interior. Blocks are unit cubes centered on their position.
+pub fn interior_mesh(
+    interior: &VoxelData,
+    blocks: &Blocks,
+    registry: &BlockRegistry,
+    light: &LightField,
+) -> Mesh {
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut colors = Vec::new();
+    let mut indices = Vec::new();
+
+    for (pos, block) in interior.iter() {
+        let Some(uv) = blocks.uv(block) else {
+            continue;
+        };
+        for face in &FACES {
+            let neighbour = interior.get(pos + face.normal);
+            if is_opaque(neighbour, registry) || is_inside_fluid(block, neighbour) {
+                continue;
+            }
+            let center = pos.as_vec3() + face.normal.as_vec3() * 0.5;
+            let right = face.right * 0.5;
+            let up = face.up * 0.5;
+            let start = positions.len() as u32;
+            positions.extend([
+                center - right - up,
+                center + right - up,
+                center + right + up,
+                center - right + up,
+            ]);
+            if let BlockType::Fluid(fluid) = block {
+                let surface = pos.y as f32 - 0.5 + fluid_height(interior, pos, fluid);
+                for position in &mut positions[start as usize..] {
+                    position.y = position.y.min(surface);
+                }
+            }
+            normals.extend([face.normal.as_vec3(); 4]);
+            uvs.extend([
+                Vec2::new(uv.min.x, uv.max.y),
+                uv.max,
+                Vec2::new(uv.max.x, uv.min.y),
+                uv.min,
+            ]);
+
+            let front = pos + face.normal;
+            let lit = light.block(front) as f32 / MAX_LIGHT as f32;
+            let brightness = UNLIT_BRIGHTNESS + (1. - UNLIT_BRIGHTNESS) * lit;
+            let (right, up) = (face.right.as_ivec3(), face.up.as_ivec3());
+            let ao = [(-1, -1), (1, -1), (1, 1), (-1, 1)]
+                .map(|(x, y)| occlusion(interior, registry, front, right * x, up * y));
+            colors.extend(ao.map(|ao| {
+                let value = AO_BRIGHTNESS[ao] * brightness;
+                [value, value, value, 1.]
+            }));
+            // Split the quad along the darker diagonal, so the occlusion of a single corner
+            // fades across the face instead of showing up as a sharp triangle.
+            if ao[0] + ao[2] < ao[1] + ao[3] {
+                indices.extend([start + 1, start + 2, start + 3, start + 1, start + 3, start]);
+            } else {
+                indices.extend([start, start + 1, start + 2, start, start + 2, start + 3]);
+            }
+        }
+    }
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+/// Builds the collider of an interior out of the solid blocks the player can actually touch.
+/// Returns `None` when there is nothing to collide with.
+pub fn interior_collider(interior: &VoxelData, registry: &BlockRegistry) -> Option<Collider> {
+    let is_solid = |pos: IVec3| {
+        interior
+            .get(pos)
+            .is_some_and(|block| registry.is_solid(block))
+    };
+    let shapes: Vec<_> = interior
+        .iter()
+        .filter(|(pos, block)| {
+            registry.is_solid(block) && FACES.iter().any(|face| !is_solid(*pos + face.normal))
+        })
+        .map(|(pos, _)| {
+            (
+                pos.as_vec3(),
+                Quat::IDENTITY,
+                Collider::cuboid(0.5, 0.5, 0.5),
+            )
+        })
+        .collect();
+    (!shapes.is_empty()).then(|| Collider::compound(shapes))
+}
+
+/// Spawn the entity rendering the interior the player is in.
+pub fn spawn_interior(
+    commands: &mut Commands,
+    interior: &VoxelData,
+    blocks: &Blocks,
+    registry: &BlockRegistry,
+    light: &LightField,
+    meshes: &mut Assets<Mesh>,
+) {
+    let mut entity = commands.spawn((
+        Name::new("Interior"),
+        StateScoped(Screen::VoxelWorld),
+        InteriorMesh(interior.clone()),
+        PbrBundle {
+            mesh: meshes.add(interior_mesh(interior, blocks, registry, light)),
+            material: blocks.material(),
+            ..Default::default()
+        },
+    ));
+    if let Some(collider) = interior_collider(interior, registry) {
+        entity.insert(collider);
+    }
+}
+
+/// Rebuild the mesh and collider whenever the interior changes. Other interiors changing leaves
+/// them as they are.
+fn update_interior_mesh(
+    mut commands: Commands,
+    interiors: Res<Interiors>,
+    hex_select: Res<HexSelect>,
+    blocks: Res<Blocks>,
+    registry: Res<BlockRegistry>,
+    lights: Res<InteriorLights>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut query: Query<(Entity, &Handle<Mesh>, &mut InteriorMesh)>,
+) {
+    let (Some(interior), Some(light)) = (
+        interiors.get(hex_select.hex_id),
+        lights.get(hex_select.hex_id),
+    ) else {
+        return;
+    };
+    for (entity, mesh, mut meshed) in &mut query {
+        if meshed.0.shares_blocks(interior) {
+            continue;
+        }
+        meshed.0 = interior.clone();
+        meshes.insert(mesh, interior_mesh(interior, &blocks, &registry, light));
+        match interior_collider(interior, &registry) {
+            Some(collider) => commands.entity(entity).insert(collider),
+            None => commands.entity(entity).remove::<Collider>(),
+        };
+    }
+}
@@ -0,0 +1,267 @@
+//! Breaking blocks in the voxel world.
+//! In survival, holding the mine button breaks the targeted block after its hardness in seconds,
+//! divided by the speed of the selected tool. A crack overlay on the targeted face shows progress.
+//! In creative, blocks break instantly and aren't collected.
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+};
+use rand::{Rng, SeedableRng};
+
+use super::{
+    interaction::target,
+    interiors::Interiors,
+    inventory::Inventory,
+    machines::Machines,
+    registry::{BlockRegistry, BlockSound},
+    voxel_util::VoxelPlayer,
+    BlockType,
+};
+use crate::{
+    game::{
+        audio::sfx::PlaySfx,
+        input::{Action, Actions},
+        mode::GameMode,
+    },
+    screen::{HexSelect, Screen},
+    AppSet,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<Mining>();
+    app.add_systems(OnEnter(Screen::VoxelWorld), spawn_crack_overlay);
+    app.add_systems(OnExit(Screen::VoxelWorld), stop_mining);
+    app.add_systems(
+        Update,
+        (
+            tick_mining.in_set(AppSet::TickTimers),
+            (break_block, update_crack_overlay)
+                .chain()
+                .in_set(AppSet::Update),
+        )
+            .run_if(in_state(Screen::VoxelWorld).and_then(resource_exists::<BlockRegistry>)),
+    );
+}
+
+/// The block being broken and how far along it is.
+struct BreakProgress {
+    pos: IVec3,
+    /// Normal of the face the player is looking at, where the cracks are shown.
+    normal: IVec3,
+    timer: Timer,
+}
+
+#[derive(Resource, Default)]
+struct Mining(Option<BreakProgress>);
+
+fn tick_mining(time: Res<Time>, mut mining: ResMut<Mining>) {
+    if let Some(progress) = &mut mining.0 {
+        progress.timer.tick(time.delta());
+    }
+}
+
+fn stop_mining(mut mining: ResMut<Mining>) {
+    mining.0 = None;
+}
+
+fn break_block(
+    mut commands: Commands,
+    actions: Actions,
+    camera: Query<&GlobalTransform, With<VoxelPlayer>>,
+    hex_select: Res<HexSelect>,
+    mut interiors: ResMut<Interiors>,
+    mut inventory: ResMut<Inventory>,
+    mut mining: ResMut<Mining>,
+    mut machines: ResMut<Machines>,
+    registry: Res<BlockRegistry>,
+    mode: Res<GameMode>,
+) {
+    let creative = *mode == GameMode::Creative;
+    let mining_input = if creative {
+        actions.just_pressed(Action::Mine)
+    } else {
+        actions.pressed(Action::Mine)
+    };
+    let hit = mining_input
+        .then(|| target(&camera, &interiors, &hex_select))
+        .flatten();
+    let Some(hit) = hit else {
+        if mining.0.is_some() {
+            mining.0 = None;
+        }
+        return;
+    };
+    let Some(block) = interiors
+        .get(hex_select.hex_id)
+        .and_then(|interior| interior.get(hit.pos))
+        .cloned()
+    else {
+        return;
+    };
+
+    if !creative {
+        // Looking at another block starts over.
+        if mining.0.as_ref().map(|progress| progress.pos) != Some(hit.pos) {
+            let speed = registry.mining_speed(inventory.selected_slot().resource_type.as_ref());
+            let duration = registry.hardness(&block) / speed;
+            mining.0 = Some(BreakProgress {
+                pos: hit.pos,
+                normal: hit.normal,
+                timer: Timer::from_seconds(duration.max(0.), TimerMode::Once),
+            });
+        }
+        if mining
+            .0
+            .as_ref()
+            .is_some_and(|progress| !progress.timer.finished())
+        {
+            return;
+        }
+        let drop = match &block {
+            BlockType::Block(id) => BlockType::Block(registry.drop(*id)),
+            other => other.clone(),
+        };
+        // Whatever a machine held goes with it, so it is only broken when all of it fits.
+        let mut contents = vec![(drop, 1)];
+        if let Some(machine) = machines.get(hex_select.hex_id, hit.pos) {
+            contents.extend(
+                machine
+                    .items()
+                    .map(|(item, quantity)| (item.clone(), quantity)),
+            );
+        }
+        let mut filled = inventory.clone();
+        if !contents
+            .into_iter()
+            .all(|(item, quantity)| filled.add_resource(item, quantity, &registry))
+        {
+            return;
+        }
+        *inventory = filled;
+    }
+
+    mining.0 = None;
+    machines.remove(hex_select.hex_id, hit.pos);
+    if let Some(interior) = interiors.get_mut(hex_select.hex_id) {
+        interior.set(hit.pos, BlockType::Air);
+    }
+    if let Some(id) = block.appearance() {
+        commands.trigger(PlaySfx::Block {
+            block: id,
+            sound: BlockSound::Break,
+            position: hit.pos.as_vec3(),
+        });
+    }
+}
+
+/// Marker for the quad drawing cracks over the block being broken.
+#[derive(Component)]
+struct CrackOverlay;
+
+/// Side of the crack texture, in pixels. Matches the block textures.
+const CRACK_SIZE: u32 = 16;
+
+/// Number of steps the cracks grow in. The material only changes when a new one is reached.
+const CRACK_STAGES: u32 = 10;
+
+/// A texture of cracks spreading from the center. The alpha of each pixel is how early it
+/// cracks, so masking out more of the texture as the block breaks makes the cracks grow.
+fn crack_image() -> Image {
+    let size = CRACK_SIZE as i32;
+    let mut alpha = vec![0u8; (size * size) as usize];
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    for _ in 0..5 {
+        let mut pos = IVec2::splat(size / 2);
+        let heading = IVec2::new(rng.gen_range(-1..=1), rng.gen_range(-1..=1));
+        for step in 0..size {
+            if pos.cmplt(IVec2::ZERO).any() || pos.cmpge(IVec2::splat(size)).any() {
+                break;
+            }
+            let pixel = &mut alpha[(pos.x + pos.y * size) as usize];
+            *pixel = (*pixel).max(255 - (step * 240 / size) as u8);
+            pos += heading + IVec2::new(rng.gen_range(-1..=1), rng.gen_range(-1..=1));
+        }
+    }
+    let data = alpha.iter().flat_map(|alpha| [0, 0, 0, *alpha]).collect();
+    let mut image = Image::new(
+        Extent3d {
+            width: CRACK_SIZE,
+            height: CRACK_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::nearest();
+    image
+}
+
+fn spawn_crack_overlay(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Name::new("Crack Overlay"),
+        CrackOverlay,
+        StateScoped(Screen::VoxelWorld),
+        PbrBundle {
+            mesh: meshes.add(Rectangle::new(1., 1.)),
+            material: materials.add(StandardMaterial {
+                base_color_texture: Some(images.add(crack_image())),
+                alpha_mode: AlphaMode::Mask(1.),
+                unlit: true,
+                ..default()
+            }),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+    ));
+}
+
+fn update_crack_overlay(
+    mining: Res<Mining>,
+    mut overlays: Query<
+        (&mut Transform, &mut Visibility, &Handle<StandardMaterial>),
+        With<CrackOverlay>,
+    >,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (mut transform, mut visibility, material) in &mut overlays {
+        let Some(progress) = mining
+            .0
+            .as_ref()
+            .filter(|progress| progress.normal != IVec3::ZERO)
+        else {
+            visibility.set_if_neq(Visibility::Hidden);
+            continue;
+        };
+        visibility.set_if_neq(Visibility::Visible);
+        let normal = progress.normal.as_vec3();
+        // Slightly in front of the face so it doesn't flicker against it.
+        transform.set_if_neq(
+            Transform::from_translation(progress.pos.as_vec3() + normal * 0.501)
+                .with_rotation(Quat::from_rotation_arc(Vec3::Z, normal)),
+        );
+        let stage = (progress.timer.fraction() * CRACK_STAGES as f32).floor();
+        // Pixels that never crack are fully transparent and must stay masked out.
+        let alpha_mode = AlphaMode::Mask((1. - stage / CRACK_STAGES as f32).max(0.05));
+        // Changing the material uploads it again, so it is only touched when the stage changes.
+        if materials
+            .get(material)
+            .is_some_and(|material| material.alpha_mode != alpha_mode)
+        {
+            if let Some(material) = materials.get_mut(material) {
+                material.alpha_mode = alpha_mode;
+            }
+        }
+    }
+}
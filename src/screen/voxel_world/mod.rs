@@ -1,15 +1,36 @@
 //! The screen state for the voxel world game loop.
+mod creative;
+mod falling;
+pub mod fluid;
+pub mod footsteps;
+mod growth;
+mod interaction;
+pub mod interiors;
 pub mod inventory;
+pub mod light;
+pub mod machines;
+mod mesh;
+mod mining;
 mod player_controller;
+pub mod registry;
+mod simulation;
+mod sky;
 mod ui;
-mod voxel_util;
+pub mod voxel_util;
 
 use super::{MapDirection, Screen};
 use crate::game::{assets::SoundtrackKey, audio::soundtrack::PlaySoundtrack};
-use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use bevy::{prelude::*, utils::HashMap};
+use inventory::Inventory;
+use player_controller::Flight;
+use registry::BlockId;
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
 use std::sync::Arc;
-use ui::{cleanup_inventory_ui, setup_inventory_ui};
-use voxel_util::{spawn_voxel_map, Blocks};
+use ui::{
+    cleanup_inventory_ui, setup_flight_hud, setup_inventory_ui, update_flight_hud,
+    update_inventory_ui,
+};
+use voxel_util::spawn_voxel_map;
 
 pub(super) fn plugin(app: &mut App) {
     app.add_systems(
@@ -18,21 +39,39 @@ pub(super) fn plugin(app: &mut App) {
             enter_playing,
             spawn_voxel_map,
             setup_inventory_ui.after(spawn_voxel_map),
+            setup_flight_hud,
         ),
     );
     app.add_systems(
         OnExit(Screen::VoxelWorld),
         (exit_playing, cleanup_inventory_ui),
     );
-    //.add_systems(Update, update_inventory_ui);
-
     app.add_systems(
         Update,
-        return_to_hex_map
-            .run_if(in_state(Screen::VoxelWorld).and_then(input_just_pressed(KeyCode::Escape))),
+        update_inventory_ui
+            .run_if(in_state(Screen::VoxelWorld).and_then(resource_changed::<Inventory>)),
+    );
+    app.add_systems(
+        Update,
+        update_flight_hud.run_if(in_state(Screen::VoxelWorld).and_then(resource_changed::<Flight>)),
     );
-    app.init_resource::<Blocks>();
-    app.add_plugins(player_controller::VoxelCamera);
+    app.init_resource::<Inventory>();
+    app.add_plugins((
+        player_controller::VoxelCamera,
+        creative::plugin,
+        falling::plugin,
+        footsteps::plugin,
+        interaction::plugin,
+        interiors::plugin,
+        light::plugin,
+        machines::plugin,
+        mesh::plugin,
+        mining::plugin,
+        registry::plugin,
+        simulation::plugin,
+        sky::plugin,
+        voxel_util::plugin,
+    ));
 }
 
 fn enter_playing(mut commands: Commands) {
@@ -44,11 +83,7 @@ fn exit_playing(mut commands: Commands) {
     commands.trigger(PlaySoundtrack::Disable);
 }
 
-fn return_to_hex_map(mut next_screen: ResMut<NextState<Screen>>) {
-    next_screen.set(Screen::HexMap);
-}
-
-const VOXEL_DIVISION_FACTOR: usize = 16;
+pub const VOXEL_DIVISION_FACTOR: usize = 16;
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct VoxelData(Arc<[BlockType; VOXEL_DIVISION_FACTOR.pow(3)]>);
@@ -61,20 +96,114 @@ impl Default for VoxelData {
     }
 }
 
-#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+impl VoxelData {
+    /// Index of `pos` in the flattened grid, or `None` if `pos` lies outside of it.
+    fn index(pos: IVec3) -> Option<usize> {
+        let size = VOXEL_DIVISION_FACTOR as i32;
+        if pos.cmplt(IVec3::ZERO).any() || pos.cmpge(IVec3::splat(size)).any() {
+            return None;
+        }
+        Some((pos.x + pos.z * size + pos.y * size * size) as usize)
+    }
+
+    /// Position in the grid of the block stored at `index`.
+    fn position(index: usize) -> IVec3 {
+        let size = VOXEL_DIVISION_FACTOR;
+        IVec3::new(
+            (index % size) as i32,
+            (index / (size * size)) as i32,
+            (index / size % size) as i32,
+        )
+    }
+
+    pub fn get(&self, pos: IVec3) -> Option<&BlockType> {
+        Self::index(pos).map(|index| &self.0[index])
+    }
+
+    /// Replaces the block at `pos`. Positions outside of the grid are ignored.
+    pub fn set(&mut self, pos: IVec3, block: BlockType) {
+        if let Some(index) = Self::index(pos) {
+            Arc::make_mut(&mut self.0)[index] = block;
+        }
+    }
+
+    /// Whether this grid still shares its blocks with `other`. Grids are copied on write,
+    /// so this tells that neither changed since one was cloned from the other, without comparing
+    /// every block.
+    pub fn shares_blocks(&self, other: &VoxelData) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Iterates over every block in the grid along with its position.
+    pub fn iter(&self) -> impl Iterator<Item = (IVec3, &BlockType)> {
+        self.0
+            .iter()
+            .enumerate()
+            .map(|(index, block)| (Self::position(index), block))
+    }
+
+    /// The registered block the grid is mostly made of. Ties go to the lowest id.
+    pub fn dominant_block(&self) -> Option<BlockId> {
+        let mut counts = HashMap::<BlockId, usize>::default();
+        for block in self.0.iter() {
+            if let BlockType::Block(id) = block {
+                *counts.entry(*id).or_default() += 1;
+            }
+        }
+        counts
+            .into_iter()
+            .max_by_key(|(id, count)| (*count, std::cmp::Reverse(*id)))
+            .map(|(id, _)| id)
+    }
+}
+
+// Serde doesn't implement its traits for arrays this large, so the grid is stored as a flat list.
+impl Serialize for VoxelData {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for VoxelData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let blocks = Vec::<BlockType>::deserialize(deserializer)?;
+        let len = blocks.len();
+        let blocks: Box<[BlockType; VOXEL_DIVISION_FACTOR.pow(3)]> = blocks
+            .into_boxed_slice()
+            .try_into()
+            .map_err(|_| D::Error::invalid_length(len, &"one block per voxel"))?;
+        Ok(VoxelData(Arc::from(blocks)))
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct DirectedVoxel {
     direction: Option<MapDirection>,
     voxel: VoxelData,
 }
 
 /// All block types
-#[derive(Debug, Hash, PartialEq, Eq, strum_macros::EnumIter, Clone)]
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub enum BlockType {
     Air,
-    Stone,
-    Coal,
+    /// A block described by the [`registry::BlockRegistry`].
+    Block(BlockId),
     Voxel(VoxelData),
     MultiVoxel(Vec<DirectedVoxel>),
+    /// A fluid described by the [`registry::BlockRegistry`], along with how much of it there is.
+    Fluid(fluid::Fluid),
+}
+
+impl BlockType {
+    /// The registered block whose texture and sounds are used for this one.
+    /// A voxel looks like the block it is mostly made of.
+    pub fn appearance(&self) -> Option<BlockId> {
+        match self {
+            BlockType::Block(id) | BlockType::Fluid(fluid::Fluid { id, .. }) => Some(*id),
+            BlockType::Voxel(voxel) => voxel.dominant_block(),
+            BlockType::Air | BlockType::MultiVoxel(_) => None,
+        }
+    }
 }
 
 // For Multi-Voxel mixing ensure that if 2 voxels can be compressed into a singular one that they are resolved as a  single voxel, not a MultiVoxel
@@ -1,4 +1,11 @@
-use crate::screen::{voxel_world::voxel_util::VoxelPlayer, Screen};
+use crate::{
+    game::{
+        input::{Action, Actions},
+        mode::GameMode,
+        settings::Settings,
+    },
+    screen::{pause::Pause, voxel_world::voxel_util::VoxelPlayer, Screen},
+};
 use bevy::{
     ecs::event::ManualEventReader,
     input::mouse::MouseMotion,
@@ -13,49 +20,111 @@ impl Plugin for VoxelCamera {
     fn build(&self, app: &mut App) {
         app.init_resource::<VoxelSettings>()
             .init_resource::<InputState>()
-            .add_systems(Update, cursor_toggle)
+            .init_resource::<Flight>()
             .add_systems(
                 Update,
-                (player_look, player_move, apply_jump, player_jump).chain(),
+                (
+                    cursor_toggle,
+                    (toggle_flight, player_look, player_move).chain(),
+                )
+                    .run_if(in_state(Screen::VoxelWorld).and_then(in_state(Pause::Running))),
             )
+            .add_systems(Update, apply_fov.run_if(in_state(Screen::VoxelWorld)))
             .add_systems(OnEnter(Screen::VoxelWorld), cursor_grab)
-            .add_systems(OnExit(Screen::VoxelWorld), cursor_release);
+            .add_systems(OnExit(Screen::VoxelWorld), (cursor_release, land))
+            .add_systems(OnEnter(Pause::Paused), cursor_release)
+            // The screen has already changed when the pause is left by leaving the voxel world.
+            .add_systems(
+                OnExit(Pause::Paused),
+                cursor_grab.run_if(in_state(Screen::VoxelWorld)),
+            );
     }
 }
 
 fn player_move(
-    mut player: Query<&mut bevy_rapier3d::prelude::KinematicCharacterController>,
-    camera: Query<(&Transform, &Parent), With<VoxelPlayer>>,
     settings: Res<VoxelSettings>,
-    input: Res<ButtonInput<KeyCode>>,
+    actions: Actions,
     time: Res<Time>,
+    flight: Res<Flight>,
+    camera: Query<(&Transform, &Parent), With<VoxelPlayer>>,
+    mut bodies: Query<
+        (
+            &mut Transform,
+            &mut KinematicCharacterController,
+            &mut CharacterMotion,
+            Option<&KinematicCharacterControllerOutput>,
+        ),
+        Without<VoxelPlayer>,
+    >,
 ) {
+    let dt = time.delta_seconds();
+    let intent = actions.movement();
     for (camera, body) in &camera {
-        let mut delta = Vec3::ZERO;
-        if input.pressed(settings.move_forward) {
-            delta.z += 1.;
-        }
-        if input.pressed(settings.move_backward) {
-            delta.z -= 1.;
+        let Ok((mut transform, mut controller, mut motion, output)) = bodies.get_mut(body.get())
+        else {
+            warn!("Voxel Player should be child of player controller");
+            continue;
+        };
+        let forward = camera.forward().as_vec3().with_y(0.).normalize_or_zero();
+        let right = camera.right().as_vec3().with_y(0.).normalize_or_zero();
+        let horizontal = forward * intent.y + right * intent.x;
+
+        if flight.flying {
+            let mut vertical = 0.;
+            if actions.pressed(Action::Jump) {
+                vertical += 1.;
+            }
+            if actions.pressed(Action::Crouch) {
+                vertical -= 1.;
+            }
+            let speed = if actions.pressed(Action::Sprint) {
+                settings.fly_sprint_speed
+            } else {
+                settings.fly_speed
+            };
+            let translation = (horizontal + Vec3::Y * vertical) * speed * dt;
+            *motion = CharacterMotion::default();
+            if flight.noclip {
+                // Moving the body directly skips collisions entirely.
+                controller.translation = None;
+                transform.translation += translation;
+            } else {
+                controller.translation = Some(translation);
+            }
+            continue;
         }
-        if input.pressed(settings.move_left) {
-            delta.x -= 1.;
+
+        // The output is from the previous move, which is as recent as it gets.
+        if let Some(output) = output {
+            if output.grounded {
+                motion.airborne = 0.;
+                motion.vertical_velocity = motion.vertical_velocity.max(0.);
+            } else {
+                motion.airborne += dt;
+            }
+            // Stop rising when bumping into a ceiling.
+            if output.effective_translation.y < output.desired_translation.y - f32::EPSILON {
+                motion.vertical_velocity = motion.vertical_velocity.min(0.);
+            }
         }
-        if input.pressed(settings.move_right) {
-            delta.x += 1.;
+
+        if actions.just_pressed(Action::Jump) && motion.airborne <= settings.coyote_time {
+            motion.vertical_velocity = settings.jump_velocity;
+            // Prevents jumping again in the air before the controller reports leaving the ground.
+            motion.airborne = settings.coyote_time + dt;
         }
-        let mut forward = camera.forward().as_vec3();
-        forward.y = 0.;
-        forward = forward.normalize();
-        let mut right = camera.right().as_vec3();
-        right.y = 0.;
-        right = right.normalize();
-        let next = (forward * delta.z + right * delta.x) * time.delta_seconds() * 10.;
-        if let Ok(mut controller) = player.get_mut(body.get()) {
-            controller.translation = Some(next);
+        motion.vertical_velocity =
+            (motion.vertical_velocity - settings.gravity * dt).max(-settings.max_fall_speed);
+
+        let speed = if actions.pressed(Action::Crouch) {
+            settings.crouch_speed
+        } else if actions.pressed(Action::Sprint) {
+            settings.sprint_speed
         } else {
-            warn!("Voxel Player should be child of player controller");
-        }
+            settings.move_speed
+        };
+        controller.translation =
+            Some((horizontal * speed + Vec3::Y * motion.vertical_velocity) * dt);
     }
 }
 
@@ -65,38 +134,68 @@ struct InputState {
     reader_motion: ManualEventReader<MouseMotion>,
 }
 
+/// Mouse sensitivity when the sensitivity setting is 1.
+const BASE_SENSITIVITY: f32 = 0.00012;
+
+/// How fast the camera turns with the right stick fully tilted, in radians per second.
+const STICK_LOOK_SPEED: f32 = 3.;
+
 fn player_look(
-    settings: Res<VoxelSettings>,
+    settings: Res<Settings>,
     primary_window: Query<&Window, With<PrimaryWindow>>,
     mut state: ResMut<InputState>,
     motion: Res<Events<MouseMotion>>,
+    actions: Actions,
+    time: Res<Time>,
     mut query: Query<&mut Transform, With<VoxelPlayer>>,
 ) {
-    if let Ok(window) = primary_window.get_single() {
-        for mut transform in query.iter_mut() {
-            for ev in state.reader_motion.read(&motion) {
-                let (mut yaw, mut pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
-                match window.cursor.grab_mode {
-                    CursorGrabMode::None => (),
-                    _ => {
-                        // Using smallest of height or width ensures equal vertical and horizontal sensitivity
-                        let window_scale = window.height().min(window.width());
-                        pitch -=
-                            (settings.mouse_sensitivity * ev.delta.y * window_scale).to_radians();
-                        yaw -=
-                            (settings.mouse_sensitivity * ev.delta.x * window_scale).to_radians();
-                    }
-                }
+    let Ok(window) = primary_window.get_single() else {
+        warn!("Primary window not found for `player_look`!");
+        return;
+    };
+    let mouse: Vec2 = state.reader_motion.read(&motion).map(|ev| ev.delta).sum();
 
-                pitch = pitch.clamp(-1.54, 1.54);
+    // How much to turn by, in radians, as (yaw, pitch).
+    let mut turn = Vec2::ZERO;
+    if window.cursor.grab_mode != CursorGrabMode::None {
+        // Using smallest of height or width ensures equal vertical and horizontal sensitivity
+        let window_scale = window.height().min(window.width());
+        let sensitivity =
+            (BASE_SENSITIVITY * settings.mouse_sensitivity * window_scale).to_radians();
+        turn -= mouse * sensitivity;
+    }
+    let stick = actions.look();
+    turn += Vec2::new(-stick.x, stick.y)
+        * STICK_LOOK_SPEED
+        * settings.mouse_sensitivity
+        * time.delta_seconds();
+    if settings.invert_y {
+        turn.y = -turn.y;
+    }
+    if turn == Vec2::ZERO {
+        return;
+    }
 
-                // Order is important to prevent unintended roll
-                transform.rotation =
-                    Quat::from_axis_angle(Vec3::Y, yaw) * Quat::from_axis_angle(Vec3::X, pitch);
-            }
+    for mut transform in &mut query {
+        let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+        let yaw = yaw + turn.x;
+        let pitch = (pitch + turn.y).clamp(-1.54, 1.54);
+
+        // Order is important to prevent unintended roll
+        transform.rotation =
+            Quat::from_axis_angle(Vec3::Y, yaw) * Quat::from_axis_angle(Vec3::X, pitch);
+    }
+}
+
+/// Keep the field of view of the camera in sync with the settings.
+fn apply_fov(settings: Res<Settings>, mut cameras: Query<(Ref<VoxelPlayer>, &mut Projection)>) {
+    for (player, mut projection) in &mut cameras {
+        if !settings.is_changed() && !player.is_added() {
+            continue;
+        }
+        if let Projection::Perspective(perspective) = projection.as_mut() {
+            perspective.fov = settings.fov.to_radians();
         }
-    } else {
-        warn!("Primary window not found for `player_look`!");
     }
 }
 
@@ -118,13 +217,9 @@ fn cursor_grab(mut primary_window: Query<&mut Window, With<PrimaryWindow>>) {
     }
 }
 
-fn cursor_toggle(
-    keys: Res<ButtonInput<KeyCode>>,
-    key_bindings: Res<VoxelSettings>,
-    mut primary_window: Query<&mut Window, With<PrimaryWindow>>,
-) {
+fn cursor_toggle(actions: Actions, mut primary_window: Query<&mut Window, With<PrimaryWindow>>) {
     if let Ok(mut window) = primary_window.get_single_mut() {
-        if keys.just_pressed(key_bindings.toggle_grab_cursor) {
+        if actions.just_pressed(Action::ToggleCursor) {
             match window.cursor.grab_mode {
                 CursorGrabMode::None => {
                     window.cursor.grab_mode = CursorGrabMode::Confined;
@@ -141,75 +236,75 @@ fn cursor_toggle(
     }
 }
 
+/// How the player moves in the voxel world. Speeds are in blocks per second.
 #[derive(Resource)]
 pub struct VoxelSettings {
-    pub move_forward: KeyCode,
-    pub move_backward: KeyCode,
-    pub move_left: KeyCode,
-    pub move_right: KeyCode,
-    pub jump: KeyCode,
-    pub toggle_grab_cursor: KeyCode,
+    /// Walking speed.
     pub move_speed: f32,
-    pub mouse_sensitivity: f32,
+    pub sprint_speed: f32,
+    pub crouch_speed: f32,
+    /// Downward acceleration, in blocks per second squared.
+    pub gravity: f32,
+    pub max_fall_speed: f32,
+    /// Upward speed when jumping. Jumps are `jump_velocity² / (2 * gravity)` blocks high.
+    pub jump_velocity: f32,
+    /// How long the player can still jump after walking off a ledge, in seconds.
+    pub coyote_time: f32,
+    pub fly_speed: f32,
+    pub fly_sprint_speed: f32,
 }
 
 impl Default for VoxelSettings {
     fn default() -> Self {
         Self {
-            move_forward: KeyCode::KeyW,
-            move_backward: KeyCode::KeyS,
-            move_left: KeyCode::KeyA,
-            move_right: KeyCode::KeyD,
-            jump: KeyCode::Space,
-            toggle_grab_cursor: KeyCode::Backquote,
-            mouse_sensitivity: 0.00012,
-            move_speed: 12.,
+            move_speed: 5.,
+            sprint_speed: 8.,
+            crouch_speed: 2.,
+            gravity: 25.,
+            max_fall_speed: 50.,
+            jump_velocity: 8.5,
+            coyote_time: 0.1,
+            fly_speed: 10.,
+            fly_sprint_speed: 20.,
         }
     }
 }
 
-#[derive(Component)]
-struct Jump {
-    left: f32,
+/// Vertical movement of the player's body, which the character controller doesn't track itself.
+#[derive(Component, Default)]
+pub struct CharacterMotion {
+    /// Upward speed, in blocks per second.
+    vertical_velocity: f32,
+    /// Time since the body last touched the ground, in seconds.
+    airborne: f32,
 }
 
-// jumping does not disable gravity so a value less then 9.8 will not make you move up
-const JUMP_POWER: f32 = 9.8 * 3.;
+/// Whether the player flies instead of walking. Only possible in [`GameMode::Creative`].
+/// While flying, jump and crouch move up and down.
+#[derive(Resource, Default)]
+pub struct Flight {
+    pub flying: bool,
+    /// Fly through blocks.
+    pub noclip: bool,
+}
 
-fn apply_jump(
-    mut commands: Commands,
-    mut jumping: Query<(Entity, &mut Jump, &mut KinematicCharacterController)>,
-    time: Res<Time>,
-) {
-    // the max jump for this frame
-    let max_jump = JUMP_POWER * time.delta_seconds();
-    for (player, mut jump, mut controller) in &mut jumping {
-        let jump_power = max_jump.min(jump.left);
-        info!("jump to use {}", jump_power);
-        let to_move = if let Some(other) = controller.translation {
-            other + Vec3::Y * jump_power
-        } else {
-            Vec3::Y * jump_power
-        };
-        controller.translation = Some(to_move);
-        jump.left -= jump_power;
-        if jump.left <= 0. {
-            commands.entity(player).remove::<Jump>();
+fn toggle_flight(actions: Actions, mode: Res<GameMode>, mut flight: ResMut<Flight>) {
+    if *mode != GameMode::Creative {
+        if flight.flying {
+            *flight = Flight::default();
         }
+        return;
+    }
+    if actions.just_pressed(Action::ToggleFlight) {
+        flight.flying = !flight.flying;
+        flight.noclip = false;
+    }
+    if flight.flying && actions.just_pressed(Action::ToggleNoclip) {
+        flight.noclip = !flight.noclip;
     }
 }
 
-fn player_jump(
-    input: Res<ButtonInput<KeyCode>>,
-    settings: Res<VoxelSettings>,
-    mut commands: Commands,
-    players: Query<(Entity, &KinematicCharacterControllerOutput), With<VoxelPlayer>>,
-) {
-    if input.just_pressed(settings.jump) {
-        for (entity, output) in &players {
-            if output.grounded {
-                commands.entity(entity).insert(Jump { left: 3. });
-            }
-        }
-    }
+/// The player always walks when entering a cell.
+fn land(mut flight: ResMut<Flight>) {
+    *flight = Flight::default();
 }
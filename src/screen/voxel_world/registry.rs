@@ -0,0 +1,517 @@
+//! The block registry describes every block that can exist in the voxel world.
+//! Blocks are read from a RON asset at startup, so adding a block (or a modded one)
+//! only needs a new entry in that file rather than changes to the code.
+
+use std::fmt;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::HashMap,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{fluid::Fluid, BlockType};
+use crate::game::assets::{DataKey, HandleMap};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(Update, build_registry);
+}
+
+/// Identifies a block in the [`BlockRegistry`].
+/// Ids are assigned in the order blocks appear in the registry file.
+/// Saves store blocks by id, so new blocks should be added at the end of the file.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Reflect, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct BlockId(u16);
+
+impl BlockId {
+    /// An id that doesn't come from a registry, for tests.
+    #[cfg(test)]
+    pub(super) const fn from_raw(id: u16) -> Self {
+        Self(id)
+    }
+}
+
+/// Everything there is to know about a single kind of block.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BlockDefinition {
+    /// Unique name used to refer to the block from other definitions.
+    pub name: String,
+    /// Texture used on every face of the block.
+    #[serde(default)]
+    pub texture: Option<String>,
+    /// Color of the block on maps, and its fallback when it has no texture.
+    #[serde(default = "defaults::color")]
+    pub color: [f32; 3],
+    /// Whether the player collides with the block.
+    #[serde(default = "defaults::solid")]
+    pub solid: bool,
+    /// How long the block takes to break, in seconds with bare hands.
+    #[serde(default = "defaults::hardness")]
+    pub hardness: f32,
+    /// Name of the block added to the inventory when this one is broken. Defaults to itself.
+    #[serde(default)]
+    pub drop: Option<String>,
+    /// How many of this block fit in a single inventory slot.
+    #[serde(default = "defaults::stack_size")]
+    pub stack_size: u32,
+    /// Icon of hex cells made of this block. Blocks without one never show up on the hex map.
+    #[serde(default)]
+    pub hex_icon: Option<String>,
+    /// Sounds played when interacting with the block.
+    #[serde(default)]
+    pub sounds: BlockSoundPaths,
+    /// Makes this a tool rather than a block: it speeds up mining while selected
+    /// and can't be placed.
+    #[serde(default)]
+    pub tool: Option<ToolDefinition>,
+    /// Light emitted by the block, from 0 to [`MAX_LIGHT`](super::light::MAX_LIGHT).
+    #[serde(default)]
+    pub light: u8,
+    /// Makes this a fluid: placing it creates a source that flows into the Air around it.
+    #[serde(default)]
+    pub fluid: Option<FluidDefinition>,
+    /// Whether the block falls when there is nothing under it.
+    #[serde(default)]
+    pub gravity: bool,
+    /// Makes this a machine that works on its own, see [`super::machines`].
+    #[serde(default)]
+    pub machine: Option<MachineDefinition>,
+    /// Makes the block grow into another one over time, see [`super::growth`].
+    #[serde(default)]
+    pub growth: Option<GrowthDefinition>,
+}
+
+/// What a tool does when it is held.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ToolDefinition {
+    /// How many times faster blocks break than with bare hands.
+    pub speed: f32,
+}
+
+/// How a fluid flows.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FluidDefinition {
+    /// Level lost for every block the fluid spreads sideways. Higher spreads less far.
+    #[serde(default = "defaults::falloff")]
+    pub falloff: u8,
+    /// Fluid steps between each time the fluid flows. Higher flows slower.
+    #[serde(default = "defaults::delay")]
+    pub delay: u32,
+    /// Block this fluid turns into when it touches another fluid, by name of that fluid.
+    #[serde(default)]
+    pub reactions: HashMap<String, String>,
+}
+
+/// What a machine does and how often.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MachineDefinition {
+    pub kind: MachineKind,
+    /// Seconds between each time the machine works.
+    #[serde(default = "defaults::machine_interval")]
+    pub interval: f32,
+    /// Number of inventory slots the machine stores items in.
+    #[serde(default = "defaults::machine_slots")]
+    pub slots: usize,
+    /// Whether the machine can face up and down, rather than only sideways.
+    #[serde(default)]
+    pub vertical: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub enum MachineKind {
+    /// Breaks the block in front of it and passes what it drops on to the machine behind it.
+    Miner,
+    /// Passes items on to the machine in front of it.
+    Conveyor,
+    /// Turns the inputs of a recipe into its output, passed on to the machine in front of it.
+    Furnace(Vec<Recipe>),
+    /// Stores items until the player takes them.
+    Container,
+}
+
+/// What a block grows into and when.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GrowthDefinition {
+    /// Name of the block it grows into.
+    pub into: String,
+    /// Light needed right above the block to grow, from 0 to [`MAX_LIGHT`](super::light::MAX_LIGHT).
+    #[serde(default)]
+    pub light: u8,
+    /// Chance of growing each time the block is picked by a growth step.
+    #[serde(default = "defaults::growth_chance")]
+    pub chance: f32,
+}
+
+/// Amounts of blocks, by name, turned into an amount of another block.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Recipe {
+    pub inputs: Vec<(String, u32)>,
+    pub output: (String, u32),
+}
+
+/// Something that happens to a block and makes a sound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum BlockSound {
+    Break,
+    Place,
+    Step,
+}
+
+/// Paths of the sounds of a block. Missing sounds fall back to the generic step sounds.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BlockSoundPaths {
+    #[serde(default, rename = "break")]
+    pub break_sound: Option<String>,
+    #[serde(default)]
+    pub place: Option<String>,
+    #[serde(default)]
+    pub step: Option<String>,
+}
+
+impl BlockSoundPaths {
+    pub fn get(&self, sound: BlockSound) -> Option<&str> {
+        match sound {
+            BlockSound::Break => self.break_sound.as_deref(),
+            BlockSound::Place => self.place.as_deref(),
+            BlockSound::Step => self.step.as_deref(),
+        }
+    }
+}
+
+mod defaults {
+    pub fn color() -> [f32; 3] {
+        [1., 0., 1.]
+    }
+
+    pub fn solid() -> bool {
+        true
+    }
+
+    pub fn hardness() -> f32 {
+        1.
+    }
+
+    pub fn stack_size() -> u32 {
+        64
+    }
+
+    pub fn machine_interval() -> f32 {
+        1.
+    }
+
+    pub fn machine_slots() -> usize {
+        4
+    }
+
+    pub fn falloff() -> u8 {
+        1
+    }
+
+    pub fn delay() -> u32 {
+        1
+    }
+
+    pub fn growth_chance() -> f32 {
+        1.
+    }
+}
+
+/// The registry file as it is stored on disk.
+#[derive(Asset, TypePath, Debug, Deserialize)]
+pub struct BlockDefinitions {
+    blocks: Vec<BlockDefinition>,
+}
+
+#[derive(Default)]
+pub struct BlockDefinitionsLoader;
+
+#[derive(Debug)]
+pub enum BlockDefinitionsLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl fmt::Display for BlockDefinitionsLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockDefinitionsLoaderError::Io(error) => {
+                write!(f, "could not read block registry: {error}")
+            }
+            BlockDefinitionsLoaderError::Ron(error) => {
+                write!(f, "could not parse block registry: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BlockDefinitionsLoaderError {}
+
+impl From<std::io::Error> for BlockDefinitionsLoaderError {
+    fn from(error: std::io::Error) -> Self {
+        BlockDefinitionsLoaderError::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for BlockDefinitionsLoaderError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        BlockDefinitionsLoaderError::Ron(error)
+    }
+}
+
+impl AssetLoader for BlockDefinitionsLoader {
+    type Asset = BlockDefinitions;
+    type Settings = ();
+    type Error = BlockDefinitionsLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["blocks.ron"]
+    }
+}
+
+/// All registered blocks. This is inserted once the registry file has loaded,
+/// and replaced whenever the file changes.
+#[derive(Resource, Debug)]
+pub struct BlockRegistry {
+    blocks: Vec<BlockDefinition>,
+    by_name: HashMap<String, BlockId>,
+}
+
+impl BlockRegistry {
+    fn new(blocks: Vec<BlockDefinition>) -> Self {
+        let by_name = blocks
+            .iter()
+            .enumerate()
+            .map(|(id, block)| (block.name.clone(), BlockId(id as u16)))
+            .collect();
+        BlockRegistry { blocks, by_name }
+    }
+
+    /// A registry read from the contents of a registry file, for tests.
+    #[cfg(test)]
+    pub(super) fn from_ron(file: &str) -> Self {
+        let definitions: BlockDefinitions = ron::from_str(file).unwrap();
+        BlockRegistry::new(definitions.blocks)
+    }
+
+    /// The definition of `id`. `None` for ids this registry doesn't have, like ones saved with
+    /// another registry file.
+    pub fn get(&self, id: BlockId) -> Option<&BlockDefinition> {
+        self.blocks.get(id.0 as usize)
+    }
+
+    /// Whether every block in `block` is in this registry, including those nested in voxels.
+    pub fn knows(&self, block: &BlockType) -> bool {
+        match block {
+            BlockType::Air => true,
+            BlockType::Block(id) | BlockType::Fluid(Fluid { id, .. }) => self.get(*id).is_some(),
+            BlockType::Voxel(voxel) => voxel.iter().all(|(_, block)| self.knows(block)),
+            BlockType::MultiVoxel(voxels) => voxels
+                .iter()
+                .all(|directed| directed.voxel.iter().all(|(_, block)| self.knows(block))),
+        }
+    }
+
+    /// Looks up a block by the name it was registered with.
+    pub fn id(&self, name: &str) -> Option<BlockId> {
+        self.by_name.get(name).copied()
+    }
+
+    /// Looks up a block by name, wrapped as a [`BlockType`].
+    pub fn block(&self, name: &str) -> Option<BlockType> {
+        self.id(name).map(BlockType::Block)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (BlockId, &BlockDefinition)> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .map(|(id, block)| (BlockId(id as u16), block))
+    }
+
+    /// Whether the player collides with `block`. Voxels are always solid.
+    pub fn is_solid(&self, block: &BlockType) -> bool {
+        match block {
+            BlockType::Air | BlockType::Fluid(_) => false,
+            BlockType::Block(id) => self.get(*id).map_or(defaults::solid(), |block| block.solid),
+            BlockType::Voxel(_) | BlockType::MultiVoxel(_) => true,
+        }
+    }
+
+    /// Whether `block` hides the faces of its neighbours and stops light. Solid blocks do, so
+    /// blocks like saplings that the player walks through let their surroundings show.
+    pub fn is_opaque(&self, block: &BlockType) -> bool {
+        match block {
+            BlockType::Air | BlockType::Fluid(_) => false,
+            BlockType::Block(id) => self.get(*id).is_some_and(|block| block.solid),
+            BlockType::Voxel(_) | BlockType::MultiVoxel(_) => true,
+        }
+    }
+
+    /// How long `block` takes to break with bare hands, in seconds.
+    /// Voxels are as hard as the block they are mostly made of.
+    pub fn hardness(&self, block: &BlockType) -> f32 {
+        block
+            .appearance()
+            .and_then(|id| self.get(id))
+            .map_or(defaults::hardness(), |block| block.hardness)
+    }
+
+    /// How many of `block` fit in a single inventory slot.
+    pub fn stack_size(&self, block: &BlockType) -> u32 {
+        match block {
+            BlockType::Block(id) => self
+                .get(*id)
+                .map_or(defaults::stack_size(), |block| block.stack_size.max(1)),
+            _ => defaults::stack_size(),
+        }
+    }
+
+    /// How many times faster blocks break while holding `item`.
+    pub fn mining_speed(&self, item: Option<&BlockType>) -> f32 {
+        match item {
+            Some(BlockType::Block(id)) => self
+                .get(*id)
+                .and_then(|block| block.tool.as_ref())
+                .map_or(1., |tool| tool.speed),
+            _ => 1.,
+        }
+    }
+
+    /// Light emitted by `block`.
+    pub fn light(&self, block: &BlockType) -> u8 {
+        match block {
+            BlockType::Block(id) | BlockType::Fluid(Fluid { id, .. }) => self
+                .get(*id)
+                .map_or(0, |block| block.light.min(super::light::MAX_LIGHT)),
+            _ => 0,
+        }
+    }
+
+    /// Whether `block` can be put in the world. Tools can't.
+    /// Fluids are placed from their block, see [`Self::placed`].
+    pub fn is_placeable(&self, block: &BlockType) -> bool {
+        match block {
+            BlockType::Air | BlockType::Fluid(_) => false,
+            BlockType::Block(id) => self.get(*id).is_some_and(|block| block.tool.is_none()),
+            BlockType::Voxel(_) | BlockType::MultiVoxel(_) => true,
+        }
+    }
+
+    /// Whether `block` falls when there is nothing under it.
+    pub fn falls(&self, block: &BlockType) -> bool {
+        match block {
+            BlockType::Block(id) => self.get(*id).is_some_and(|block| block.gravity),
+            _ => false,
+        }
+    }
+
+    /// What `block` does, if it is a machine.
+    pub fn machine(&self, block: &BlockType) -> Option<&MachineDefinition> {
+        match block {
+            BlockType::Block(id) => self.get(*id)?.machine.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// How `block` grows, if it does.
+    pub fn growth(&self, block: &BlockType) -> Option<&GrowthDefinition> {
+        match block {
+            BlockType::Block(id) => self.get(*id)?.growth.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// A recipe resolved to blocks. `None` if it refers to a block missing from the registry.
+    pub fn recipe(&self, recipe: &Recipe) -> Option<(Vec<(BlockType, u32)>, (BlockType, u32))> {
+        let inputs = recipe
+            .inputs
+            .iter()
+            .map(|(name, amount)| Some((self.block(name)?, *amount)))
+            .collect::<Option<_>>()?;
+        let (name, amount) = &recipe.output;
+        Some((inputs, (self.block(name)?, *amount)))
+    }
+
+    /// How `id` flows, if it is a fluid.
+    pub fn fluid(&self, id: BlockId) -> Option<&FluidDefinition> {
+        self.get(id)?.fluid.as_ref()
+    }
+
+    /// What ends up in the world when `block` is placed. Fluid blocks become sources.
+    pub fn placed(&self, block: BlockType) -> BlockType {
+        match block {
+            BlockType::Block(id) if self.fluid(id).is_some() => BlockType::Fluid(Fluid::source(id)),
+            other => other,
+        }
+    }
+
+    /// The block given to the player when `id` is broken.
+    pub fn drop(&self, id: BlockId) -> BlockId {
+        self.get(id)
+            .and_then(|block| block.drop.as_deref())
+            .and_then(|name| self.id(name))
+            .unwrap_or(id)
+    }
+
+    /// Human readable name of `block`.
+    pub fn name<'a>(&'a self, block: &BlockType) -> &'a str {
+        match block {
+            BlockType::Air => "air",
+            BlockType::Block(id) | BlockType::Fluid(Fluid { id, .. }) => {
+                self.get(*id).map_or("unknown", |block| &block.name)
+            }
+            BlockType::Voxel(_) => "voxel",
+            BlockType::MultiVoxel(_) => "multi voxel",
+        }
+    }
+}
+
+fn build_registry(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<BlockDefinitions>>,
+    data_handles: Res<HandleMap<DataKey>>,
+    definitions: Res<Assets<BlockDefinitions>>,
+) {
+    for event in events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = event
+        else {
+            continue;
+        };
+        if *id != data_handles[&DataKey::Blocks].id() {
+            continue;
+        }
+        if let Some(definitions) = definitions.get(*id) {
+            commands.insert_resource(BlockRegistry::new(definitions.blocks.clone()));
+        }
+    }
+}
+
+#[test]
+fn unknown_blocks() {
+    let registry = BlockRegistry::from_ron(r#"(blocks: [(name: "stone"), (name: "water")])"#);
+    let unknown = BlockType::Block(BlockId(2));
+    assert!(registry.get(BlockId(1)).is_some());
+    assert!(registry.get(BlockId(2)).is_none());
+    assert!(registry.knows(&BlockType::Block(BlockId(1))));
+    assert!(!registry.knows(&unknown));
+    assert_eq!(registry.name(&unknown), "unknown");
+
+    let mut voxel = super::VoxelData::default();
+    voxel.set(IVec3::ZERO, unknown);
+    assert!(!registry.knows(&BlockType::Voxel(voxel)));
+}
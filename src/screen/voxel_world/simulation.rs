@@ -0,0 +1,208 @@
+//! The simulation of every interior, whether the player is in it or not.
+//! It runs on the stored [`Interiors`] and [`Machines`] rather than on entities: every step,
+//! fluids flow, machines work and blocks grow. The interior the player is in takes every step as
+//! it comes. The others take theirs in batches every [`BACKGROUND_INTERVAL`] steps, spread over
+//! time so they don't all catch up at once, and entering an interior catches up on what it missed.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    ops::RangeInclusive,
+    time::Duration,
+};
+
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashMap};
+
+use super::{
+    fluid, growth,
+    interiors::Interiors,
+    light::{InteriorLights, LightSet},
+    machines::{self, Machines},
+    registry::BlockRegistry,
+    voxel_util::spawn_voxel_map,
+};
+use crate::{
+    game::save::LoadGame,
+    screen::{hex_map::cells::HexId, pause::Pause, HexSelect, Screen},
+    AppSet,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<Simulation>();
+    app.observe(reset_simulation);
+    app.add_systems(
+        OnEnter(Screen::VoxelWorld),
+        catch_up_on_enter
+            .before(spawn_voxel_map)
+            .run_if(resource_exists::<BlockRegistry>),
+    );
+    app.add_systems(
+        Update,
+        (
+            tick_simulation.in_set(AppSet::TickTimers),
+            // Before the changed interiors are relit.
+            simulate.in_set(AppSet::Update).before(LightSet),
+        )
+            .run_if(in_state(Pause::Running).and_then(resource_exists::<BlockRegistry>)),
+    );
+}
+
+/// Time between two steps of the simulation.
+const STEP: Duration = Duration::from_millis(250);
+
+/// Steps between each batch of an interior the player isn't in.
+const BACKGROUND_INTERVAL: u64 = 8;
+
+/// Most steps an interior catches up on at once. Older ones are skipped.
+const MAX_CATCH_UP: u64 = 240;
+
+#[derive(Resource)]
+pub struct Simulation {
+    timer: Timer,
+    /// Steps taken so far.
+    step: u64,
+    /// Last step taken by each interior.
+    stepped: HashMap<HexId, u64>,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Simulation {
+            timer: Timer::new(STEP, TimerMode::Repeating),
+            step: 0,
+            stepped: HashMap::default(),
+        }
+    }
+}
+
+/// Everything the simulation changes.
+#[derive(SystemParam)]
+struct Cells<'w> {
+    interiors: ResMut<'w, Interiors>,
+    machines: ResMut<'w, Machines>,
+    lights: ResMut<'w, InteriorLights>,
+    registry: Res<'w, BlockRegistry>,
+}
+
+impl Simulation {
+    /// Takes every step the interior of `id` missed so far.
+    fn catch_up(&mut self, cells: &mut Cells, id: HexId) {
+        let last = self.stepped.insert(id, self.step).unwrap_or(self.step);
+        let steps = missed_steps(last, self.step);
+        if steps.is_empty() {
+            return;
+        }
+        // Holding on to the interiors a step can reach tells which of them changed, without
+        // marking them changed and remeshing when nothing happened.
+        let interiors = cells.interiors.bypass_change_detection();
+        let before: Vec<_> = std::iter::once(id)
+            .chain(id.neighbors())
+            .map(|id| (id, interiors.get(id).cloned()))
+            .collect();
+        let mut rng = rand::thread_rng();
+        for step in steps {
+            fluid::flow(interiors, &cells.registry, id, step);
+            machines::run(&mut cells.machines, interiors, &cells.registry, id, STEP);
+            growth::grow(interiors, &mut cells.lights, &cells.registry, id, &mut rng);
+        }
+        let changed = before
+            .iter()
+            .any(|(id, old)| match (old, interiors.get(*id)) {
+                (Some(old), Some(new)) => !old.shares_blocks(new),
+                (old, new) => old.is_some() != new.is_some(),
+            });
+        if changed {
+            cells.interiors.set_changed();
+        }
+    }
+}
+
+/// The steps an interior that last stepped at `last` takes to catch up on `step`,
+/// at most the last [`MAX_CATCH_UP`] of them.
+fn missed_steps(last: u64, step: u64) -> RangeInclusive<u64> {
+    (last + 1).max(step.saturating_sub(MAX_CATCH_UP) + 1)..=step
+}
+
+/// Whether the interior of `id` takes its steps at `step`, while the player is in `active`.
+fn is_due(id: HexId, step: u64, active: Option<HexId>) -> bool {
+    Some(id) == active || (step + offset(id)) % BACKGROUND_INTERVAL == 0
+}
+
+/// Where in [`BACKGROUND_INTERVAL`] the batches of `id` fall.
+fn offset(id: HexId) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    id.hash(&mut hasher);
+    hasher.finish() % BACKGROUND_INTERVAL
+}
+
+fn tick_simulation(time: Res<Time>, mut simulation: ResMut<Simulation>) {
+    simulation.timer.tick(time.delta());
+}
+
+fn simulate(
+    mut simulation: ResMut<Simulation>,
+    mut cells: Cells,
+    screen: Res<State<Screen>>,
+    hex_select: Res<HexSelect>,
+) {
+    let active = (*screen.get() == Screen::VoxelWorld).then_some(hex_select.hex_id);
+    for _ in 0..simulation.timer.times_finished_this_tick() {
+        simulation.step += 1;
+        let step = simulation.step;
+        let ids: Vec<_> = cells.interiors.iter().map(|(id, _)| id).collect();
+        for id in ids {
+            // New interiors start from the step they appeared at.
+            simulation.stepped.entry(id).or_insert(step - 1);
+            if is_due(id, step, active) {
+                simulation.catch_up(&mut cells, id);
+            }
+        }
+    }
+}
+
+fn catch_up_on_enter(
+    mut simulation: ResMut<Simulation>,
+    mut cells: Cells,
+    hex_select: Res<HexSelect>,
+) {
+    simulation.catch_up(&mut cells, hex_select.hex_id);
+}
+
+/// Steps of another game don't apply to the one being loaded.
+fn reset_simulation(_trigger: Trigger<LoadGame>, mut commands: Commands) {
+    commands.insert_resource(Simulation::default());
+}
+
+#[test]
+fn catching_up_is_bounded() {
+    assert_eq!(missed_steps(10, 10), 11..=10);
+    assert_eq!(missed_steps(10, 12), 11..=12);
+    assert_eq!(
+        missed_steps(0, BACKGROUND_INTERVAL),
+        1..=BACKGROUND_INTERVAL
+    );
+    let steps = missed_steps(3, 10_000);
+    assert_eq!(steps.clone().count() as u64, MAX_CATCH_UP);
+    assert_eq!(*steps.end(), 10_000);
+}
+
+#[test]
+fn background_interiors_step_in_batches() {
+    let active = HexId::new(0, 0);
+    let steps = 1..=BACKGROUND_INTERVAL * 10;
+    assert!(steps.clone().all(|step| is_due(active, step, Some(active))));
+    for id in active.spiral(3).skip(1) {
+        let due: Vec<_> = steps
+            .clone()
+            .filter(|step| is_due(id, *step, Some(active)))
+            .collect();
+        assert_eq!(due.len(), 10);
+        assert!(due
+            .windows(2)
+            .all(|pair| pair[1] - pair[0] == BACKGROUND_INTERVAL));
+        // Batches take every step since the last one.
+        assert!(due
+            .windows(2)
+            .all(|pair| missed_steps(pair[0], pair[1]).count() as u64 == BACKGROUND_INTERVAL));
+    }
+}
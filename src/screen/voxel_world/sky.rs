@@ -0,0 +1,109 @@
+//! The sun and the day cycle of the voxel world.
+
+use std::f32::consts::TAU;
+
+use bevy::{pbr::light_consts::lux, prelude::*};
+
+use crate::{screen::Screen, AppSet};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<DayCycle>();
+    app.add_systems(OnEnter(Screen::VoxelWorld), (save_ambient, spawn_sun));
+    app.add_systems(OnExit(Screen::VoxelWorld), restore_ambient);
+    app.add_systems(
+        Update,
+        (
+            tick_day_cycle.in_set(AppSet::TickTimers),
+            update_sun.in_set(AppSet::Update),
+        )
+            .run_if(in_state(Screen::VoxelWorld)),
+    );
+}
+
+/// Length of a full day and night, in seconds.
+const DAY_LENGTH: f32 = 600.;
+
+/// Ambient light at night and at noon.
+const NIGHT_AMBIENT: f32 = 40.;
+const DAY_AMBIENT: f32 = 400.;
+
+/// Time of day. Starts in the morning.
+#[derive(Resource)]
+pub struct DayCycle(Timer);
+
+impl Default for DayCycle {
+    fn default() -> Self {
+        let mut timer = Timer::from_seconds(DAY_LENGTH, TimerMode::Repeating);
+        timer.set_elapsed(std::time::Duration::from_secs_f32(DAY_LENGTH * 0.05));
+        Self(timer)
+    }
+}
+
+impl DayCycle {
+    /// Direction towards the sun. It rises in +X, is overhead at noon and sets in -X.
+    pub fn sun_direction(&self) -> Vec3 {
+        let angle = self.0.fraction() * TAU;
+        Vec3::new(angle.cos(), angle.sin(), 0.3).normalize()
+    }
+
+    /// How much of the day's light reaches the ground, from 0 at night to 1 at noon.
+    pub fn daylight(&self) -> f32 {
+        self.sun_direction().y.max(0.)
+    }
+}
+
+/// The ambient light of the other screens, put back when leaving the voxel world.
+#[derive(Resource)]
+struct OutsideAmbient(AmbientLight);
+
+/// Marker for the light of the sun.
+#[derive(Component)]
+struct Sun;
+
+fn tick_day_cycle(time: Res<Time>, mut cycle: ResMut<DayCycle>) {
+    cycle.0.tick(time.delta());
+}
+
+fn save_ambient(mut commands: Commands, ambient: Res<AmbientLight>) {
+    commands.insert_resource(OutsideAmbient(ambient.clone()));
+}
+
+fn restore_ambient(
+    mut commands: Commands,
+    outside: Option<Res<OutsideAmbient>>,
+    mut ambient: ResMut<AmbientLight>,
+) {
+    if let Some(outside) = outside {
+        *ambient = outside.0.clone();
+        commands.remove_resource::<OutsideAmbient>();
+    }
+}
+
+fn spawn_sun(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Sun"),
+        Sun,
+        StateScoped(Screen::VoxelWorld),
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                shadows_enabled: true,
+                ..default()
+            },
+            ..default()
+        },
+    ));
+}
+
+fn update_sun(
+    cycle: Res<DayCycle>,
+    mut suns: Query<(&mut Transform, &mut DirectionalLight), With<Sun>>,
+    mut ambient: ResMut<AmbientLight>,
+) {
+    let daylight = cycle.daylight();
+    for (mut transform, mut light) in &mut suns {
+        *transform = Transform::default().looking_to(-cycle.sun_direction(), Vec3::Z);
+        light.illuminance = lux::AMBIENT_DAYLIGHT * daylight;
+    }
+    ambient.brightness = NIGHT_AMBIENT + (DAY_AMBIENT - NIGHT_AMBIENT) * daylight;
+    ambient.color = Color::srgb(0.5, 0.55, 0.8).mix(&Color::WHITE, daylight);
+}
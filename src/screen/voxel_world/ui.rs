@@ -1,16 +1,30 @@
-use super::voxel_util::VoxelPlayer;
-use crate::screen::voxel_world::inventory::Inventory;
-use crate::ui::widgets::{Containers, UiRoot, Widgets};
-use bevy::prelude::*; // Adjust this path as needed
-
-pub fn setup_inventory_ui(mut commands: Commands, player_query: Query<(&Inventory, &VoxelPlayer)>) {
-    if let Ok(player_inventory) = player_query.get_single() {
-        commands
-            .ui_root() // Assuming you have this method from the Containers trait
-            .with_children(|parent| {
-                parent.inventory(player_inventory.0);
-            });
-    }
+use crate::game::mode::GameMode;
+use crate::screen::voxel_world::{
+    inventory::Inventory, player_controller::Flight, registry::BlockRegistry,
+};
+use crate::screen::Screen;
+use crate::ui::{
+    palette::LABEL_TEXT,
+    widgets::{Containers, UiRoot, Widgets},
+};
+use bevy::{prelude::*, ui::Val::*}; // Adjust this path as needed
+
+/// Marker for the UI root holding the inventory, so it can be rebuilt when the inventory changes.
+#[derive(Component)]
+pub struct InventoryUi;
+
+pub fn setup_inventory_ui(
+    mut commands: Commands,
+    inventory: Res<Inventory>,
+    registry: Res<BlockRegistry>,
+    mode: Res<GameMode>,
+) {
+    commands
+        .ui_root() // Assuming you have this method from the Containers trait
+        .insert(InventoryUi)
+        .with_children(|parent| {
+            parent.inventory(&inventory, *mode == GameMode::Creative, &registry);
+        });
 }
 
 pub fn cleanup_inventory_ui(mut commands: Commands, ui_query: Query<Entity, With<UiRoot>>) {
@@ -19,21 +33,60 @@ pub fn cleanup_inventory_ui(mut commands: Commands, ui_query: Query<Entity, With
     }
 }
 
-/*pub fn update_inventory_ui(
+pub fn update_inventory_ui(
     mut commands: Commands,
-    player_query: Query<&Inventory, Changed<Inventory>>,
-    ui_root_query: Query<Entity, With<UiRoot>>, // Assuming you have a UiRoot component
+    inventory: Res<Inventory>,
+    registry: Res<BlockRegistry>,
+    mode: Res<GameMode>,
+    ui_root_query: Query<Entity, With<InventoryUi>>,
 ) {
-    if let Ok(player_inventory) = player_query.get_single() {
-        if let Ok(ui_root) = ui_root_query.get_single() {
-            // Remove the old inventory UI
-            commands.entity(ui_root).despawn_descendants();
-
-            // Spawn the new inventory UI
-            commands.entity(ui_root).with_children(|parent| {
-                parent.inventory(player_inventory);
-            });
-        }
+    for ui_root in &ui_root_query {
+        // Remove the old inventory UI
+        commands.entity(ui_root).despawn_descendants();
+
+        // Spawn the new inventory UI
+        commands.entity(ui_root).with_children(|parent| {
+            parent.inventory(&inventory, *mode == GameMode::Creative, &registry);
+        });
+    }
+}
+
+/// Text in the corner of the screen telling whether the player is flying.
+#[derive(Component)]
+pub struct FlightHud;
+
+fn flight_text(flight: &Flight) -> &'static str {
+    match (flight.flying, flight.noclip) {
+        (false, _) => "",
+        (true, false) => "Flying",
+        (true, true) => "Flying (noclip)",
+    }
+}
+
+pub fn setup_flight_hud(mut commands: Commands, flight: Res<Flight>) {
+    commands.spawn((
+        Name::new("Flight HUD"),
+        FlightHud,
+        StateScoped(Screen::VoxelWorld),
+        TextBundle::from_section(
+            flight_text(&flight),
+            TextStyle {
+                font_size: 24.0,
+                color: LABEL_TEXT,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Px(10.0),
+            left: Px(10.0),
+            ..default()
+        }),
+    ));
+}
+
+pub fn update_flight_hud(flight: Res<Flight>, mut texts: Query<&mut Text, With<FlightHud>>) {
+    for mut text in &mut texts {
+        text.sections[0].value = flight_text(&flight).to_string();
     }
 }
-*/
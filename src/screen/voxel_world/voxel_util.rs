@@ -1,24 +1,55 @@
-use crate::screen::{voxel_world::player_controller::VoxelCamera, HexSelect, MapDirection, Screen};
-use bevy::{prelude::*, utils::HashMap};
+use crate::{
+    game::assets::HandleMap,
+    screen::{HexSelect, MapDirection, Screen},
+};
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+    sprite::TextureAtlasBuilder,
+    utils::HashMap,
+};
 use bevy_rapier3d::prelude::*;
-use rand::{Rng, SeedableRng};
-use strum::IntoEnumIterator;
 
-use super::{inventory::Inventory, BlockType};
-
-pub struct VoxelPlugin;
-
-impl Plugin for VoxelPlugin {
-    fn build(&self, app: &mut App) {
-        app.init_resource::<Blocks>().add_plugins(VoxelCamera);
-    }
+use super::{
+    footsteps::Footsteps,
+    interiors::Interiors,
+    light::InteriorLights,
+    mesh::spawn_interior,
+    player_controller::CharacterMotion,
+    registry::{BlockId, BlockRegistry, BlockSound},
+    BlockType, VOXEL_DIVISION_FACTOR,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            setup_blocks.run_if(resource_exists_and_changed::<BlockRegistry>),
+            build_block_atlas.run_if(
+                resource_exists::<HandleMap<BlockId>>.and_then(not(resource_exists::<Blocks>)),
+            ),
+        )
+            .chain(),
+    );
 }
 
 /// This describes the main player in the voxel world
 #[derive(Component)]
 pub struct VoxelPlayer;
 
-pub fn spawn_voxel_map(mut commands: Commands, blocks: Res<Blocks>, hex_select: Res<HexSelect>) {
+pub fn spawn_voxel_map(
+    mut commands: Commands,
+    blocks: Res<Blocks>,
+    hex_select: Res<HexSelect>,
+    interiors: Res<Interiors>,
+    registry: Res<BlockRegistry>,
+    mut lights: ResMut<InteriorLights>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
     commands
         .spawn((
             StateScoped(Screen::VoxelWorld),
@@ -26,18 +57,24 @@ pub fn spawn_voxel_map(mut commands: Commands, blocks: Res<Blocks>, hex_select:
                 transform: Transform::from_translation(pos_from_enter(&hex_select.direction)),
                 ..Default::default()
             },
-            RigidBody::Dynamic,
-            LockedAxes::ROTATION_LOCKED,
+            RigidBody::KinematicPositionBased,
             Collider::capsule_y(0.5, 0.45),
-            KinematicCharacterControllerOutput::default(),
-            bevy_rapier3d::control::KinematicCharacterController {
+            KinematicCharacterController {
+                // Walk up single blocks without jumping.
+                autostep: Some(CharacterAutostep {
+                    max_height: CharacterLength::Absolute(1.05),
+                    min_width: CharacterLength::Absolute(0.3),
+                    include_dynamic_bodies: false,
+                }),
+                snap_to_ground: Some(CharacterLength::Absolute(0.3)),
                 ..Default::default()
             },
+            CharacterMotion::default(),
+            Footsteps::default(),
         ))
         .with_children(|p| {
             p.spawn((
                 VoxelPlayer,
-                Inventory::new(10),
                 Camera3dBundle {
                     camera: Camera {
                         order: 1,
@@ -46,192 +83,147 @@ pub fn spawn_voxel_map(mut commands: Commands, blocks: Res<Blocks>, hex_select:
                     transform: Transform::from_translation(Vec3::Y * 0.5),
                     ..Default::default()
                 },
+                // Block sounds are heard relative to the camera.
+                SpatialListener::new(0.3),
             ));
         });
 
-    fill_world(
-        commands,
-        hex_select.hex_id,
-        WorldType::from_u8(3), // ! FIX THIS AS WORLD TYPE SELECTION. CURRENTLY FORCES STONE. USE SEED AND SAVE DATA
-        blocks.as_ref(),
-    );
-}
-
-fn pos_from_enter(direction: &MapDirection) -> Vec3 {
-    match direction {
-        MapDirection::Down => Vec3::new(8., 0., 8.),
-        MapDirection::North => Vec3::new(16., 8., 8.),
-        MapDirection::East => Vec3::new(8., 8., 16.),
-        MapDirection::Up => Vec3::new(8., 16., 8.),
-        MapDirection::South => Vec3::new(0., 8., 8.),
-        MapDirection::West => Vec3::new(8., 8., 0.),
+    match interiors.get(hex_select.hex_id) {
+        Some(interior) => {
+            let light = lights.sync(hex_select.hex_id, interior, &*registry);
+            spawn_interior(
+                &mut commands,
+                interior,
+                &blocks,
+                &registry,
+                light,
+                &mut meshes,
+            );
+        }
+        None => warn!("No interior generated for {:?}", hex_select.hex_id),
     }
 }
 
-#[derive(PartialEq, Eq)]
-enum WorldType {
-    Empty,
-    Stone,
-    Coal,
-    Flat,
+fn pos_from_enter(direction: &MapDirection) -> Vec3 {
+    // The middle of the face on the side of `direction`.
+    let half = VOXEL_DIVISION_FACTOR as f32 / 2.;
+    Vec3::splat(half) + direction.face_normal().as_vec3() * half
 }
 
+/// All block textures packed into a single atlas, shared by every block through one material.
 #[derive(Resource)]
-pub struct Solid([bool; 16 * 16 * 16]);
-
-impl Default for Solid {
-    fn default() -> Self {
-        Self([false; 16 * 16 * 16])
-    }
-}
-
-impl Solid {
-    fn set(&mut self, x: i32, y: i32, z: i32, val: bool) {
-        self.0[(x + z * 16 + y * 16 * 16) as usize] = val;
-    }
-    fn clear(&mut self) {
-        self.0 = [false; 16 * 16 * 16];
-    }
-    pub fn get(&self, x: i32, y: i32, z: i32) -> bool {
-        self.0
-            .get((x + z * 16 + y * 16 * 16) as usize)
-            .cloned()
-            .unwrap_or(false)
-    }
+pub struct Blocks {
+    material: Handle<StandardMaterial>,
+    uvs: HashMap<BlockId, Rect>,
 }
 
-impl WorldType {
-    fn from_u8(id: u8) -> WorldType {
-        match id {
-            0 => WorldType::Empty,
-            1 => WorldType::Stone,
-            2 => WorldType::Coal,
-            3 => WorldType::Flat,
-            _ => unreachable!(),
-        }
-    }
-
-    fn sample(&self, mut rng: impl Rng, pos: IVec3) -> BlockType {
-        match self {
-            WorldType::Flat => {
-                if pos.y == 0 {
-                    BlockType::Stone
-                } else if pos.y == 1 && rand::thread_rng().gen_bool(0.1) {
-                    BlockType::Coal
-                } else if pos.y == 2 && rand::thread_rng().gen_bool(0.1) {
-                    BlockType::Coal
-                } else {
-                    BlockType::Air
-                }
-            }
-            WorldType::Empty => BlockType::Air,
-            WorldType::Stone => {
-                if rng.gen_bool(0.6) || pos.y == 0 {
-                    BlockType::Stone
-                } else {
-                    BlockType::Air
-                }
-            }
-            WorldType::Coal => {
-                if rng.gen_bool(0.3) && pos.y != 0 {
-                    BlockType::Air
-                } else if rng.gen_bool(0.25) {
-                    BlockType::Coal
-                } else {
-                    BlockType::Stone
-                }
-            }
-        }
+impl Blocks {
+    pub fn material(&self) -> Handle<StandardMaterial> {
+        self.material.clone()
     }
-}
 
-fn fill_world(mut commands: Commands, id: Vec2, world_type: WorldType, blocks: &Blocks) {
-    if world_type == WorldType::Empty {
-        return;
-    }
-    let mut rng = rand::rngs::StdRng::seed_from_u64((id.x as u64) << 32 | id.y as u64);
-    for x in 0..16 {
-        for y in 0..16 {
-            for z in 0..16 {
-                let block = world_type.sample(&mut rng, IVec3::new(x, y, z));
-                let solidity = block.is_solid();
-                let mut entity = commands.spawn((
-                    StateScoped(Screen::VoxelWorld),
-                    PbrBundle {
-                        mesh: blocks.mesh(),
-                        material: blocks.texture(block),
-                        transform: Transform::from_translation(Vec3::new(
-                            x as f32, y as f32, z as f32,
-                        )),
-                        ..Default::default()
-                    },
-                ));
-                if solidity {
-                    entity.insert(Collider::cuboid(0.5, 0.5, 0.5));
-                }
-            }
-        }
+    /// Area of the atlas covered by the texture of `block`.
+    /// Blocks without an [`appearance`](BlockType::appearance) are not rendered.
+    pub fn uv(&self, block: &BlockType) -> Option<Rect> {
+        self.uvs.get(&block.appearance()?).copied()
     }
 }
 
-impl BlockType {
-    const fn texture_path(&self) -> &'static str {
-        match self {
-            BlockType::Air => "", // ! To fix
-            BlockType::Stone => "images/voxels/stone.png",
-            BlockType::Coal => "images/voxels/coal.png",
-            BlockType::Voxel(_) => "",
-            BlockType::MultiVoxel(_) => "",
-        }
-    }
-
-    const fn is_solid(&self) -> bool {
-        match self {
-            BlockType::Air => false,
-            BlockType::Stone => true,
-            BlockType::Coal => true,
-            BlockType::Voxel(_) => false,
-            BlockType::MultiVoxel(_) => false,
-        }
-    }
+/// Start loading the textures and sounds of all registered blocks.
+/// Runs again whenever the registry is reloaded.
+/// Blocks without a texture path get a solid color texture once the atlas is built.
+fn setup_blocks(
+    mut commands: Commands,
+    registry: Res<BlockRegistry>,
+    asset_server: Res<AssetServer>,
+) {
+    let textures: HashMap<_, _> = registry
+        .iter()
+        .filter_map(|(id, block)| Some((id, asset_server.load(block.texture.clone()?))))
+        .collect();
+    commands.insert_resource(HandleMap::<BlockId>::from(textures));
+    commands.remove_resource::<Blocks>();
+
+    let sounds: HashMap<_, _> = registry
+        .iter()
+        .flat_map(|(id, block)| {
+            [BlockSound::Break, BlockSound::Place, BlockSound::Step]
+                .into_iter()
+                .filter_map(move |sound| Some(((id, sound), block.sounds.get(sound)?)))
+        })
+        .map(|(key, path)| (key, asset_server.load(path.to_string())))
+        .collect();
+    commands.insert_resource(HandleMap::<(BlockId, BlockSound)>::from(sounds));
 }
 
-#[derive(Resource)]
-pub struct Blocks {
-    mesh: Handle<Mesh>,
-    textures: HashMap<BlockType, Handle<StandardMaterial>>,
+/// A texture for blocks that only have a color.
+fn solid_color([red, green, blue]: [f32; 3]) -> Image {
+    let channel = |value: f32| (value.clamp(0., 1.) * 255.) as u8;
+    Image::new_fill(
+        Extent3d {
+            width: 16,
+            height: 16,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[channel(red), channel(green), channel(blue), 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    )
 }
 
-impl Blocks {
-    pub fn texture(&self, block: BlockType) -> Handle<StandardMaterial> {
-        self.textures.get(&block).cloned().unwrap_or_default()
-    }
-    pub fn mesh(&self) -> Handle<Mesh> {
-        self.mesh.clone()
+/// Pack the block textures into the atlas once they are all loaded.
+/// Blocks whose texture failed to load are left out and won't be rendered.
+fn build_block_atlas(
+    mut commands: Commands,
+    registry: Res<BlockRegistry>,
+    textures: Res<HandleMap<BlockId>>,
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let (done, total) = textures.progress(&asset_server);
+    if done < total {
+        return;
     }
-}
 
-impl FromWorld for Blocks {
-    fn from_world(world: &mut World) -> Self {
-        let mut blocks = Blocks {
-            mesh: world
-                .resource_mut::<Assets<Mesh>>()
-                .add(Cuboid::new(1., 1., 1.)),
-            textures: HashMap::default(),
-        };
-        let asset_server = world.resource::<AssetServer>().clone();
-        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
-        for block in BlockType::iter() {
-            let texture_path = block.texture_path();
-            blocks.textures.insert(
-                block,
-                materials.add(StandardMaterial {
-                    base_color_texture: Some(asset_server.load(texture_path)),
-                    ..Default::default()
-                }),
-            );
+    let textures: Vec<(BlockId, Handle<Image>)> = registry
+        .iter()
+        .map(|(id, block)| match textures.get(&id) {
+            Some(texture) => (id, texture.clone_weak()),
+            None => (id, images.add(solid_color(block.color))),
+        })
+        .collect();
+
+    let (layout, mut atlas) = {
+        let mut builder = TextureAtlasBuilder::default();
+        for (_, texture) in &textures {
+            if let Some(image) = images.get(texture) {
+                builder.add_texture(Some(texture.id()), image);
+            }
         }
-
-        blocks
-    }
+        match builder.build() {
+            Ok(atlas) => atlas,
+            Err(error) => {
+                error!("Could not build the block atlas: {error}");
+                return;
+            }
+        }
+    };
+    atlas.sampler = ImageSampler::nearest();
+
+    let size = layout.size.as_vec2();
+    let uvs = textures
+        .iter()
+        .filter_map(|(id, texture)| {
+            let rect = layout.textures[layout.get_texture_index(texture.id())?].as_rect();
+            Some((*id, Rect::from_corners(rect.min / size, rect.max / size)))
+        })
+        .collect();
+    let material = materials.add(StandardMaterial {
+        base_color_texture: Some(images.add(atlas)),
+        perceptual_roughness: 1.,
+        ..Default::default()
+    });
+    commands.insert_resource(Blocks { material, uvs });
 }
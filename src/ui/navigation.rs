@@ -0,0 +1,133 @@
+//! Gamepad navigation of buttons and sliders.
+//! The D-pad moves the focus between the widgets of the top-most UI root,
+//! South presses the focused button and left/right move the focused slider.
+
+use bevy::{prelude::*, ui::UiSystem, ui::Val::*};
+
+use super::{palette::LABEL_TEXT, slider::Slider};
+use crate::game::{
+    assets::SfxKey,
+    audio::sfx::PlaySfx,
+    input::{Binding, Inputs},
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<GamepadFocus>();
+    // Pressing happens right after Bevy computes interactions,
+    // so button handlers see the press like one made with the mouse.
+    app.add_systems(PreUpdate, press_focused.after(UiSystem::Focus));
+    app.add_systems(Update, (navigate, highlight_focused).chain());
+}
+
+/// The widget focused with the gamepad.
+#[derive(Resource, Default)]
+pub struct GamepadFocus(Option<Entity>);
+
+const PAD_UP: Binding = Binding::Gamepad(GamepadButtonType::DPadUp);
+const PAD_DOWN: Binding = Binding::Gamepad(GamepadButtonType::DPadDown);
+const PAD_LEFT: Binding = Binding::Gamepad(GamepadButtonType::DPadLeft);
+const PAD_RIGHT: Binding = Binding::Gamepad(GamepadButtonType::DPadRight);
+const PAD_PRESS: Binding = Binding::Gamepad(GamepadButtonType::South);
+
+/// Widgets that can be focused, in reading order.
+/// Only the top-most UI root is considered, so the focus can't wander to a screen behind a menu.
+fn focusable(
+    widgets: &Query<(Entity, &GlobalTransform), Or<(With<Button>, With<Slider>)>>,
+    parents: &Query<&Parent>,
+    z_indices: &Query<&ZIndex>,
+) -> Vec<Entity> {
+    let layer = |entity| {
+        let root = parents.iter_ancestors(entity).last().unwrap_or(entity);
+        match z_indices.get(root) {
+            Ok(ZIndex::Global(z)) => *z,
+            _ => 0,
+        }
+    };
+    let mut widgets: Vec<_> = widgets
+        .iter()
+        .map(|(entity, transform)| (entity, transform.translation(), layer(entity)))
+        .collect();
+    let top = widgets.iter().map(|(_, _, layer)| *layer).max();
+    widgets.retain(|(_, _, layer)| Some(*layer) == top);
+    widgets.sort_by(|(_, a, _), (_, b, _)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+    widgets.into_iter().map(|(entity, ..)| entity).collect()
+}
+
+fn navigate(
+    mut commands: Commands,
+    inputs: Inputs,
+    mut focus: ResMut<GamepadFocus>,
+    widgets: Query<(Entity, &GlobalTransform), Or<(With<Button>, With<Slider>)>>,
+    parents: Query<&Parent>,
+    z_indices: Query<&ZIndex>,
+) {
+    let step = if inputs.just_pressed(PAD_DOWN) {
+        1
+    } else if inputs.just_pressed(PAD_UP) {
+        -1
+    } else {
+        return;
+    };
+    let widgets = focusable(&widgets, &parents, &z_indices);
+    if widgets.is_empty() {
+        focus.0 = None;
+        return;
+    }
+    let next = match focus
+        .0
+        .and_then(|focused| widgets.iter().position(|entity| *entity == focused))
+    {
+        Some(index) => (index as isize + step).rem_euclid(widgets.len() as isize) as usize,
+        None => 0,
+    };
+    focus.0 = Some(widgets[next]);
+    commands.trigger(PlaySfx::Key(SfxKey::ButtonHover));
+}
+
+fn press_focused(
+    inputs: Inputs,
+    focus: Res<GamepadFocus>,
+    mut buttons: Query<&mut Interaction, With<Button>>,
+    mut sliders: Query<&mut Slider>,
+) {
+    let Some(entity) = focus.0 else {
+        return;
+    };
+    if inputs.just_pressed(PAD_PRESS) {
+        if let Ok(mut interaction) = buttons.get_mut(entity) {
+            *interaction = Interaction::Pressed;
+        }
+    }
+    if let Ok(mut slider) = sliders.get_mut(entity) {
+        let direction = if inputs.just_pressed(PAD_RIGHT) {
+            1.
+        } else if inputs.just_pressed(PAD_LEFT) {
+            -1.
+        } else {
+            return;
+        };
+        slider.value = (slider.value + direction * slider.step).clamp(slider.min, slider.max);
+    }
+}
+
+fn highlight_focused(
+    mut commands: Commands,
+    focus: Res<GamepadFocus>,
+    mut highlighted: Local<Option<Entity>>,
+) {
+    if !focus.is_changed() {
+        return;
+    }
+    if let Some(mut entity) = highlighted
+        .take()
+        .and_then(|entity| commands.get_entity(entity))
+    {
+        entity.remove::<Outline>();
+    }
+    if let Some(entity) = focus.0 {
+        commands
+            .entity(entity)
+            .insert(Outline::new(Px(3.0), Px(0.0), LABEL_TEXT));
+        *highlighted = Some(entity);
+    }
+}
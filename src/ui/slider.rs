@@ -0,0 +1,83 @@
+//! Sliders for picking a value in a range by dragging the mouse.
+
+use bevy::{prelude::*, ui::RelativeCursorPosition, ui::Val::*};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<Slider>();
+    app.add_systems(Update, (drag_slider, update_slider).chain());
+}
+
+/// A horizontal slider. Its value follows the cursor while it is pressed.
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct Slider {
+    pub label: String,
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+    /// The value is always a multiple of this.
+    pub step: f32,
+}
+
+impl Slider {
+    pub fn new(label: impl Into<String>, value: f32, min: f32, max: f32, step: f32) -> Self {
+        Self {
+            label: label.into(),
+            value,
+            min,
+            max,
+            step,
+        }
+    }
+
+    /// How far along the slider the value is, from 0 to 1.
+    pub(super) fn fraction(&self) -> f32 {
+        ((self.value - self.min) / (self.max - self.min)).clamp(0., 1.)
+    }
+
+    pub(super) fn text(&self) -> String {
+        let decimals = if self.step >= 1. { 0 } else { 2 };
+        format!("{}: {:.*}", self.label, decimals, self.value)
+    }
+}
+
+/// The part of a slider filled up to its value.
+#[derive(Component)]
+pub struct SliderFill;
+
+/// The label of a slider, showing its value.
+#[derive(Component)]
+pub struct SliderText;
+
+fn drag_slider(mut sliders: Query<(&Interaction, &RelativeCursorPosition, &mut Slider)>) {
+    for (interaction, cursor, mut slider) in &mut sliders {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some(cursor) = cursor.normalized else {
+            continue;
+        };
+        let value = slider.min + cursor.x.clamp(0., 1.) * (slider.max - slider.min);
+        let value = ((value / slider.step).round() * slider.step).clamp(slider.min, slider.max);
+        if value != slider.value {
+            slider.value = value;
+        }
+    }
+}
+
+fn update_slider(
+    sliders: Query<(&Slider, &Children), Changed<Slider>>,
+    mut fills: Query<&mut Style, With<SliderFill>>,
+    mut texts: Query<&mut Text, With<SliderText>>,
+) {
+    for (slider, children) in &sliders {
+        for child in children {
+            if let Ok(mut style) = fills.get_mut(*child) {
+                style.width = Percent(slider.fraction() * 100.);
+            }
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = slider.text();
+            }
+        }
+    }
+}
@@ -1,9 +1,20 @@
 //! Helper traits for creating common widgets.
 
-use bevy::{ecs::system::EntityCommands, prelude::*, ui::Val::*};
+use bevy::{
+    ecs::system::EntityCommands,
+    prelude::*,
+    ui::{RelativeCursorPosition, Val::*},
+};
 
-use super::{interaction::InteractionPalette, palette::*};
-use crate::screen::voxel_world::inventory::{Inventory, InventorySlot};
+use super::{
+    interaction::InteractionPalette,
+    palette::*,
+    slider::{Slider, SliderFill, SliderText},
+};
+use crate::screen::voxel_world::{
+    inventory::{Inventory, InventorySlot},
+    registry::BlockRegistry,
+};
 
 // Define the UiRoot component
 #[derive(Component)]
@@ -14,16 +25,35 @@ pub trait Widgets {
     /// Spawn a simple button with text.
     fn button(&mut self, text: impl Into<String>) -> EntityCommands;
 
+    /// Spawn a wide button with smaller text, for long lists of options.
+    fn small_button(&mut self, text: impl Into<String>) -> EntityCommands;
+
     /// Spawn a simple header label. Bigger than [`Widgets::label`].
     fn header(&mut self, text: impl Into<String>) -> EntityCommands;
 
     /// Spawn a simple text label.
     fn label(&mut self, text: impl Into<String>) -> EntityCommands;
-    /// Spawn an inventory slot UI element
-    fn inventory_slot(&mut self, slot: &InventorySlot) -> EntityCommands;
+
+    /// Spawn a slider showing its label and value.
+    fn slider(&mut self, slider: Slider) -> EntityCommands;
+
+    /// Spawn an inventory slot UI element.
+    /// Quantities are hidden when the inventory is `infinite`, as in creative.
+    fn inventory_slot(
+        &mut self,
+        slot: &InventorySlot,
+        selected: bool,
+        infinite: bool,
+        registry: &BlockRegistry,
+    ) -> EntityCommands;
 
     /// Spawn a complete inventory UI
-    fn inventory(&mut self, inventory: &Inventory) -> EntityCommands;
+    fn inventory(
+        &mut self,
+        inventory: &Inventory,
+        infinite: bool,
+        registry: &BlockRegistry,
+    ) -> EntityCommands;
 }
 
 impl<T: Spawn> Widgets for T {
@@ -63,6 +93,42 @@ impl<T: Spawn> Widgets for T {
         entity
     }
 
+    fn small_button(&mut self, text: impl Into<String>) -> EntityCommands {
+        let mut entity = self.spawn((
+            Name::new("Small Button"),
+            ButtonBundle {
+                style: Style {
+                    width: Px(500.0),
+                    height: Px(30.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: BackgroundColor(NODE_BACKGROUND),
+                ..default()
+            },
+            InteractionPalette {
+                none: NODE_BACKGROUND,
+                hovered: BUTTON_HOVERED_BACKGROUND,
+                pressed: BUTTON_PRESSED_BACKGROUND,
+            },
+        ));
+        entity.with_children(|children| {
+            children.spawn((
+                Name::new("Button Text"),
+                TextBundle::from_section(
+                    text,
+                    TextStyle {
+                        font_size: 20.0,
+                        color: BUTTON_TEXT,
+                        ..default()
+                    },
+                ),
+            ));
+        });
+        entity
+    }
+
     fn header(&mut self, text: impl Into<String>) -> EntityCommands {
         let mut entity = self.spawn((
             Name::new("Header"),
@@ -123,7 +189,66 @@ impl<T: Spawn> Widgets for T {
         entity
     }
 
-    fn inventory_slot(&mut self, slot: &InventorySlot) -> EntityCommands {
+    fn slider(&mut self, slider: Slider) -> EntityCommands {
+        let fraction = slider.fraction();
+        let text = slider.text();
+        let mut entity = self.spawn((
+            Name::new("Slider"),
+            NodeBundle {
+                style: Style {
+                    width: Px(500.0),
+                    height: Px(40.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: BackgroundColor(NODE_BACKGROUND),
+                ..default()
+            },
+            Interaction::default(),
+            RelativeCursorPosition::default(),
+            slider,
+        ));
+        entity.with_children(|children| {
+            children.spawn((
+                Name::new("Slider Fill"),
+                SliderFill,
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left: Px(0.0),
+                        width: Percent(fraction * 100.0),
+                        height: Percent(100.0),
+                        ..default()
+                    },
+                    background_color: BackgroundColor(BUTTON_PRESSED_BACKGROUND),
+                    ..default()
+                },
+            ));
+            children.spawn((
+                Name::new("Slider Text"),
+                SliderText,
+                TextBundle::from_section(
+                    text,
+                    TextStyle {
+                        font_size: 24.0,
+                        color: BUTTON_TEXT,
+                        ..default()
+                    },
+                ),
+            ));
+        });
+        entity
+    }
+
+    fn inventory_slot(
+        &mut self,
+        slot: &InventorySlot,
+        selected: bool,
+        infinite: bool,
+        registry: &BlockRegistry,
+    ) -> EntityCommands {
+        let border_color = if selected { LABEL_TEXT } else { Color::NONE };
         let mut entity = self.spawn((
             Name::new("Inventory Slot"),
             NodeBundle {
@@ -133,6 +258,7 @@ impl<T: Spawn> Widgets for T {
                     border: UiRect::all(Px(1.0)),
                     ..default()
                 },
+                border_color: BorderColor(border_color),
                 background_color: BackgroundColor(NODE_BACKGROUND),
                 ..default()
             },
@@ -143,7 +269,7 @@ impl<T: Spawn> Widgets for T {
                 children.spawn((
                     Name::new("Resource Type"),
                     TextBundle::from_section(
-                        format!("{:?}", resource_type),
+                        registry.name(resource_type),
                         TextStyle {
                             font_size: 12.0,
                             color: LABEL_TEXT,
@@ -152,23 +278,30 @@ impl<T: Spawn> Widgets for T {
                     ),
                 ));
             }
-            children.spawn((
-                Name::new("Quantity"),
-                TextBundle::from_section(
-                    slot.quantity.to_string(),
-                    TextStyle {
-                        font_size: 16.0,
-                        color: LABEL_TEXT,
-                        ..default()
-                    },
-                ),
-            ));
+            if !infinite {
+                children.spawn((
+                    Name::new("Quantity"),
+                    TextBundle::from_section(
+                        slot.quantity.to_string(),
+                        TextStyle {
+                            font_size: 16.0,
+                            color: LABEL_TEXT,
+                            ..default()
+                        },
+                    ),
+                ));
+            }
         });
 
         entity
     }
 
-    fn inventory(&mut self, inventory: &Inventory) -> EntityCommands {
+    fn inventory(
+        &mut self,
+        inventory: &Inventory,
+        infinite: bool,
+        registry: &BlockRegistry,
+    ) -> EntityCommands {
         let mut entity = self.spawn((
             Name::new("Inventory"),
             NodeBundle {
@@ -190,8 +323,8 @@ impl<T: Spawn> Widgets for T {
         ));
 
         entity.with_children(|children| {
-            for slot in &inventory.slots {
-                children.inventory_slot(slot);
+            for (index, slot) in inventory.slots.iter().enumerate() {
+                children.inventory_slot(slot, index == inventory.selected, infinite, registry);
             }
         });
 